@@ -0,0 +1,40 @@
+#![feature(test)]
+extern crate jit;
+extern crate test;
+use test::Bencher;
+use jit::*;
+use std::mem;
+
+fn bench_checked_sum(b: &mut Bencher, cold: bool) {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i64, usize, usize) -> i64>());
+    {
+        let data = func.param(0).unwrap();
+        let len = func.param(1).unwrap();
+        let count = func.param(2).unwrap();
+        let sum = func.var::<i64>(0);
+        func.insn_for_range(func.insn_of(0usize), count, func.insn_of(1usize), |i, _| {
+            let check = || func.insn_throw_builtin(BuiltinException::OutOfBounds);
+            func.insn_if(func.insn_geq(i, len), || if cold {
+                func.insn_cold(check)
+            } else {
+                check()
+            });
+            sum.set(sum.get() + func.insn_load_elem(data, i, typecs::get_long()));
+        });
+        func.insn_return(sum.get());
+    }
+    let values = (0..4096).collect::<Vec<i64>>();
+    func.compile().with(|comp: extern fn((*const i64, usize, usize)) -> i64| {
+        let func: extern fn(*const i64, usize, usize) -> i64 = unsafe { mem::transmute(comp) };
+        b.iter(|| func(values.as_ptr(), values.len(), values.len()));
+    });
+}
+#[bench]
+fn bench_checked_sum_inline(b: &mut Bencher) {
+    bench_checked_sum(b, false);
+}
+#[bench]
+fn bench_checked_sum_cold(b: &mut Bencher) {
+    bench_checked_sum(b, true);
+}
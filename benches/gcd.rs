@@ -12,14 +12,8 @@ fn bench_gcd(b: &mut Bencher) {
     let mut ctx = Context::<()>::new();
     jit_func!(&mut ctx, func, fn(x: usize, y:usize) -> usize {
         func.insn_if(func.insn_eq(x, y), || func.insn_return(x));
-        func.insn_if(func.insn_lt(x, y), || {
-            let mut args = [x, y - x];
-            let v = func.insn_call(Some("gcd"), func, None, &mut args, flags::CallFlags::NO_THROW);
-            func.insn_return(v);
-        });
-        let mut args = [x - y, y];
-        let temp4 = func.insn_call(Some("gcd"), func, None, &mut args, flags::CallFlags::NO_THROW);
-        func.insn_return(temp4);
+        func.insn_if(func.insn_lt(x, y), || func.insn_tail_call(func, &mut [x, y - x]).unwrap());
+        func.insn_tail_call(func, &mut [x - y, y]).unwrap();
     }, b.iter(|| assert_eq!(func(90, 50), 10) ));
 }
 #[bench]
@@ -0,0 +1,48 @@
+#![feature(custom_attribute, custom_derive, test, plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+extern crate test;
+use test::Bencher;
+use jit::*;
+
+#[repr(C)]
+#[derive(Clone, Copy, Compile)]
+struct Particle {
+    pos: (f64, f64),
+    vel: (f64, f64),
+    mass: f32
+}
+
+#[bench]
+fn bench_get_tuple(b: &mut Bencher) {
+    b.iter(|| get::<(f64, f64, i32)>());
+}
+#[bench]
+fn bench_get_cached_tuple(b: &mut Bencher) {
+    b.iter(|| get_cached::<(f64, f64, i32)>());
+}
+#[bench]
+fn bench_get_signature(b: &mut Bencher) {
+    b.iter(|| get::<fn(f64, f64) -> f64>());
+}
+#[bench]
+fn bench_get_cached_signature(b: &mut Bencher) {
+    b.iter(|| get_cached::<fn(f64, f64) -> f64>());
+}
+#[bench]
+fn bench_get_struct(b: &mut Bencher) {
+    b.iter(|| get::<Particle>());
+}
+#[bench]
+fn bench_insn_of_struct(b: &mut Bencher) {
+    let mut ctx = Context::<()>::new();
+    let particle = Particle {
+        pos: (0.0, 1.0),
+        vel: (2.0, 3.0),
+        mass: 4.0
+    };
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> Particle>());
+    b.iter(|| func.insn_of(particle));
+}
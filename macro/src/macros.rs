@@ -20,8 +20,9 @@ use syntax::ptr::P;
 use syntax::owned_slice::OwnedSlice;
 use rustc::plugin::Registry;
 
-static BAD_STRUCT:&'static str = "jit-compatible structs must be packed, mark with #[repr(packed)] to fix";
-static BAD_ITEM:&'static str = "only structs can be compatible with LibJIT";
+static BAD_STRUCT:&'static str = "jit-compatible structs must be packed or C-like, mark with #[repr(packed)] or #[repr(C)] to fix";
+static BAD_ENUM:&'static str = "jit-compatible enums must have a representation, mark with #[repr(C)] or #[repr(u8)] to fix";
+static BAD_ITEM:&'static str = "only structs and enums can be compatible with LibJIT";
 
 fn simple_type(cx: &mut ExtCtxt, name: &'static str, as_cow:bool) -> P<Expr> {
     let new_name = format!("get_{}", name);
@@ -35,7 +36,7 @@ fn simple_type(cx: &mut ExtCtxt, name: &'static str, as_cow:bool) -> P<Expr> {
 fn type_expr(cx: &mut ExtCtxt, sp: Span, ty: P<Ty>, as_cow: bool) -> P<Expr> {
     match ty.node {
         Ty_::TyParen(ref ty) => type_expr(cx, sp, ty.clone(), as_cow),
-        Ty_::TyPtr(_) | Ty_::TyRptr(_, _) => simple_type(cx, "VOID_PTR", as_cow),
+        Ty_::TyPtr(_) | Ty_::TyRptr(_, _) => simple_type(cx, "void_ptr", as_cow),
         Ty_::TyPath(ref self_, ref path) => {
             if self_.is_none() && path.segments.len() == 1 {
                 match path.segments[0].identifier.as_str() {
@@ -72,236 +73,298 @@ fn type_expr(cx: &mut ExtCtxt, sp: Span, ty: P<Ty>, as_cow: bool) -> P<Expr> {
         }
     }
 }
-fn expand_derive_compile(cx: &mut ExtCtxt, sp: Span, _meta: &MetaItem, item: &Annotatable, push: &mut FnMut(Annotatable)) {
-    let item = item.clone().expect_item();
-    let name = item.ident;
+/// Get the words in the `#[repr(...)]` attributes of an item
+fn get_repr(item: &Item) -> Vec<String> {
+    let mut repr = Vec::new();
+    for attr in &item.attrs {
+        if let MetaItem_::MetaList(ref name, ref items) = attr.node.value.node {
+            if &**name == "repr" {
+                for item in items {
+                    if let MetaItem_::MetaWord(ref text) = item.node {
+                        repr.push(text.to_string())
+                    }
+                }
+            }
+        }
+    }
+    repr
+}
+/// Wrap the type expression given so the type it makes is cached per thread,
+/// unless the type is generic and so has no single `TypeId`
+fn cache_type_expr(cx: &mut ExtCtxt, self_ty: &P<Ty>, gen: &Generics, type_expr: P<Expr>) -> P<Expr> {
+    if gen.ty_params.is_empty() && gen.lifetimes.is_empty() {
+        quote_expr!(cx, ::jit::cache_type(::std::any::TypeId::of::<$self_ty>(), || {
+            let ty: ::jit::CowType = $type_expr;
+            ty.into_owned()
+        }))
+    } else {
+        type_expr
+    }
+}
+/// Make an implementation of `Compile` for the type given with the bodies of
+/// `get_type` and `compile` given
+fn impl_compile(cx: &mut ExtCtxt, sp: Span, name: Ident, self_ty: P<Ty>, gen: &Generics,
+                get_type: P<Expr>, compile: P<Block>) -> P<Item> {
     let jit = cx.ident_of("jit");
     let jit_life = cx.lifetime(sp, token::intern("'a"));
     let jit_compile = cx.path_all(sp, false, vec![jit, cx.ident_of("Compile")], vec![jit_life], vec![], vec![]);
-    let jit_cow_type = cx.path_all(sp, false, vec![jit, cx.ident_of("CowType")], vec![cx.lifetime(sp, token::intern("'static"))], vec![], vec![]);
+    let jit_cow_type = cx.path_all(sp, false, vec![jit, cx.ident_of("CowType")], vec![jit_life], vec![], vec![]);
     let jit_func = cx.path_all(sp, false, vec![jit, cx.ident_of("UncompiledFunction")], vec![jit_life], vec![], vec![]);
     let jit_val = cx.path(sp, vec![jit, cx.ident_of("Val")]);
-    let jit_val_new = cx.path(sp, vec![jit, cx.ident_of("Val"), cx.ident_of("new")]);
     let jit_value = cx.ty_rptr(sp, cx.ty_path(jit_val), Some(jit_life), Mutability::MutImmutable);
-    let new_struct = cx.path(sp, vec![jit, cx.ident_of("Type"), cx.ident_of("new_struct")]);
     let func = cx.ident_of("func");
-    let value = cx.ident_of("value");
-    let offset = cx.ident_of("offset");
-    let mut repr = None;
-    for attr in &item.attrs {
-        if let MetaItem_::MetaList(ref name, ref items) = attr.node.value.node {
-            if &**name == "repr" && items.len() == 1 {
-                if let MetaItem_::MetaWord(ref text) = items[0].node {
-                    repr = Some(&**text)
-                }
+    cx.item(sp, name, vec![], Item_::ItemImpl(
+        Unsafety::Normal,
+        ImplPolarity::Positive,
+        Generics {
+            lifetimes: vec![ LifetimeDef {lifetime: jit_life, bounds: vec![]}],
+            ty_params: gen.ty_params.clone(),
+            where_clause: WhereClause {
+                id: DUMMY_NODE_ID,
+                predicates: gen.ty_params.iter()
+                    .map(|param| WherePredicate::BoundPredicate(
+                        WhereBoundPredicate {
+                            span: sp,
+                            bound_lifetimes: vec![],
+                            bounded_ty: cx.ty_ident(sp, param.ident),
+                            bounds: OwnedSlice::from_vec(vec![
+                                TyParamBound::TraitTyParamBound(
+                                    cx.poly_trait_ref(sp, jit_compile.clone()),
+                                    TraitBoundModifier::None
+                                ),
+                            ])
+                        }
+                    ))
+                    .collect()
             }
+        },
+        Some(cx.trait_ref(jit_compile)),
+        self_ty,
+        vec![
+            P(ImplItem {
+                attrs: vec![],
+                id: DUMMY_NODE_ID,
+                span: sp,
+                ident: cx.ident_of("get_type"),
+                vis: Visibility::Inherited,
+                node: ImplItem_::MethodImplItem(
+                    MethodSig {
+                        constness: Constness::NotConst,
+                        unsafety: Unsafety::Normal,
+                        abi: Abi::Rust,
+                        explicit_self: respan(sp, ExplicitSelf_::SelfStatic),
+                        decl: cx.fn_decl(vec![], cx.ty_path(jit_cow_type)),
+                        generics: empty_generics(),
+                    },
+                    cx.block_expr(get_type)
+                )
+            }),
+            P(ImplItem {
+                attrs: vec![],
+                id: DUMMY_NODE_ID,
+                span: sp,
+                ident: cx.ident_of("compile"),
+                vis: Visibility::Inherited,
+                node: ImplItem_::MethodImplItem(
+                    MethodSig {
+                        constness: Constness::NotConst,
+                        unsafety: Unsafety::Normal,
+                        abi: Abi::Rust,
+                        explicit_self: respan(
+                            sp,
+                            ExplicitSelf_::SelfValue(cx.ident_of("b"))),
+                        decl: cx.fn_decl(
+                            vec![
+                                Arg::new_self(sp, Mutability::MutImmutable,
+                                              cx.ident_of("self")),
+                                cx.arg(sp, func, cx.ty_rptr(sp, cx.ty_path(jit_func),
+                                                            None, Mutability::MutImmutable))],
+                            jit_value),
+                        generics: empty_generics(),
+                    },
+                    compile
+                )
+            })
+        ]
+    ))
+}
+/// Make an expression that stringifies the identifier given
+fn ident_str(cx: &mut ExtCtxt, sp: Span, ident: Ident) -> P<Expr> {
+    let expr = expand_stringify(cx, sp, &[TokenTree::TtToken(sp, Token::Ident(ident, IdentStyle::Plain))]);
+    expr.make_expr().unwrap()
+}
+fn expand_derive_compile(cx: &mut ExtCtxt, sp: Span, _meta: &MetaItem, item: &Annotatable, push: &mut FnMut(Annotatable)) {
+    let item = item.clone().expect_item();
+    let name = item.ident;
+    let jit = cx.ident_of("jit");
+    let new_struct = cx.path(sp, vec![jit, cx.ident_of("Type"), cx.ident_of("new_struct")]);
+    let repr = get_repr(&item);
+    let is_c = repr.iter().any(|repr| repr == "C");
+    let is_packed = repr.iter().any(|repr| repr == "packed");
+    let int_repr = repr.iter().find(|repr| *repr != "C" && *repr != "packed").cloned();
+    let gen = match item.node {
+        Item_::ItemEnum(_, ref gen) | Item_::ItemStruct(_, ref gen) => gen.clone(),
+        _ => {
+            cx.span_err(sp, BAD_ITEM);
+            return;
         }
-    }
+    };
+    let types = gen.ty_params.iter().map(|param| cx.ty_ident(sp, param.ident)).collect();
+    let self_ty = cx.ty_path(cx.path_all(sp, false, vec![name], vec![], types, vec![]));
+    let self_type = type_expr(cx, sp, self_ty.clone(), false);
+    let lit_usize = LitIntType::UnsignedIntLit(UintTy::TyUs);
     match item.node {
-        Item_::ItemEnum(_, _) => {
-            if let Some(kind) = repr {
-                let inner_ty = cx.ty_ident(sp, cx.ident_of(kind));
+        Item_::ItemEnum(ref def, _) => {
+            let has_data = def.variants.iter().any(|variant| match variant.node.kind {
+                VariantKind::TupleVariantKind(ref args) => args.len() > 0,
+                VariantKind::StructVariantKind(_) => true
+            });
+            if int_repr.is_none() && !is_c {
+                cx.span_err(sp, BAD_ENUM);
+                return;
+            }
+            // a #[repr(C)] enum's discriminant is a C int
+            let kind = int_repr.unwrap_or("i32".to_string());
+            let inner_ty = cx.ty_ident(sp, cx.ident_of(&kind));
+            if !has_data {
                 let type_expr = type_expr(cx, sp, inner_ty.clone(), true);
-                let expr = quote_expr!(cx, (self as $inner_ty).compile(&func));
-                let item = cx.item(sp, name, vec![], Item_::ItemImpl(
-                    Unsafety::Normal,
-                    ImplPolarity::Positive,
-                    Generics {
-                        lifetimes: vec![ LifetimeDef {lifetime: jit_life, bounds: vec![]}],
-                        ty_params: OwnedSlice::empty(),
-                        where_clause: WhereClause {
-                            id: DUMMY_NODE_ID,
-                            predicates: vec![]
+                let expr = quote_expr!(cx, (self as $inner_ty).compile(func));
+                let compiler = cx.block_expr(expr);
+                let item = impl_compile(cx, sp, name, self_ty, &gen, type_expr, compiler);
+                push(Annotatable::Item(item));
+                return;
+            }
+            let discriminant_type = type_expr(cx, sp, inner_ty, false);
+            let constructor = if is_c { "new_c_enum" } else { "new_enum" };
+            let new_enum = cx.path(sp, vec![jit, cx.ident_of("Type"), cx.ident_of(constructor)]);
+            let ty = cx.ident_of("ty");
+            let mut variants = Vec::with_capacity(def.variants.len());
+            let mut arms = Vec::with_capacity(def.variants.len());
+            let mut discriminant = quote_expr!(cx, 0isize);
+            for (index, variant) in def.variants.iter().enumerate() {
+                if let Some(ref expr) = variant.node.disr_expr {
+                    discriminant = expr.clone();
+                } else if index > 0 {
+                    discriminant = quote_expr!(cx, $discriminant + 1);
+                }
+                let path = cx.path(sp, vec![name, variant.node.name]);
+                let mut field_types = Vec::new();
+                let mut compiled = Vec::new();
+                let pat = match variant.node.kind {
+                    VariantKind::TupleVariantKind(ref args) => {
+                        let mut pats = Vec::with_capacity(args.len());
+                        for (index, arg) in args.iter().enumerate() {
+                            let field = cx.ident_of(&format!("field{}", index));
+                            field_types.push(type_expr(cx, sp, arg.ty.clone(), false));
+                            pats.push(cx.pat_ident(sp, field));
+                            compiled.push(quote_expr!(cx, $field.compile(func)));
                         }
+                        cx.pat_enum(sp, path, pats)
                     },
-                    Some(cx.trait_ref(jit_compile)),
-                    cx.ty_ident(sp, name),
-                    vec![
-                        P(ImplItem {
-                            attrs: vec![],
-                            id: DUMMY_NODE_ID,
-                            span: sp,
-                            ident: cx.ident_of("get_type"),
-                            vis: Visibility::Inherited,
-                            node: ImplItem_::MethodImplItem(
-                                MethodSig {
-                                    constness: Constness::NotConst,
-                                    unsafety: Unsafety::Normal,
-                                    abi: Abi::Rust,
-                                    explicit_self: respan(sp, ExplicitSelf_::SelfStatic),
-                                    decl: cx.fn_decl(vec![], cx.ty_path(jit_cow_type)),
-                                    generics: empty_generics(),
-                                },
-                                cx.block_expr(type_expr))
-                        }),
-                        P(ImplItem {
-                            attrs: vec![],
-                            id: DUMMY_NODE_ID,
-                            span: sp,
-                            ident: cx.ident_of("compile"),
-                            vis: Visibility::Inherited,
-                            node: ImplItem_::MethodImplItem(
-                                MethodSig {
-                                    constness: Constness::NotConst,
-                                    unsafety: Unsafety::Normal,
-                                    abi: Abi::Rust,
-                                    explicit_self: respan(
-                                        sp,
-                                        ExplicitSelf_::SelfValue(cx.ident_of("b"))),
-                                    decl: cx.fn_decl(
-                                        vec![
-                                            Arg::new_self(sp, Mutability::MutImmutable,
-                                                          cx.ident_of("self")),
-                                            cx.arg(sp, func, cx.ty_rptr(sp, cx.ty_path(jit_func),
-                                                                        None, Mutability::MutImmutable))],
-                                        jit_value),
-                                    generics: empty_generics(),
-                                },
-                                cx.block_expr(expr)
-                            )
-                        })
-                    ]
-                ));
-                push(Annotatable::Item(item));
-            } else {
-                cx.span_err(sp, BAD_ITEM)
+                    VariantKind::StructVariantKind(ref def) => {
+                        let mut pats = Vec::with_capacity(def.fields.len());
+                        for (index, field) in def.fields.iter().enumerate() {
+                            let binding = cx.ident_of(&format!("field{}", index));
+                            field_types.push(type_expr(cx, sp, field.node.ty.clone(), false));
+                            pats.push(respan(sp, FieldPat {
+                                ident: field.node.ident().unwrap(),
+                                pat: cx.pat_ident(sp, binding),
+                                is_shorthand: false
+                            }));
+                            compiled.push(quote_expr!(cx, $binding.compile(func)));
+                        }
+                        cx.pat_struct(sp, path, pats)
+                    }
+                };
+                let fields = cx.expr_mut_addr_of(sp, cx.expr_vec(sp, field_types));
+                variants.push(cx.expr_addr_of(sp, cx.expr_call(sp, cx.expr_path(new_struct.clone()), vec![fields])));
+                let compiled = cx.expr_vec_slice(sp, compiled);
+                let index = cx.expr_usize(sp, index);
+                let body = quote_expr!(cx,
+                    func.insn_make_variant($ty, $index, func.insn_of(($discriminant) as isize), $compiled));
+                arms.push(cx.arm(sp, vec![pat], body));
             }
+            let variants = cx.expr_mut_addr_of(sp, cx.expr_vec(sp, variants));
+            let type_expr = cx.expr_call(sp, cx.expr_path(new_enum), vec![discriminant_type, variants]);
+            let type_expr = quote_expr!(cx, $type_expr.into());
+            let type_expr = cache_type_expr(cx, &self_ty, &gen, type_expr);
+            let let_ty = quote_stmt!(cx, let $ty = $self_type).unwrap();
+            let matcher = cx.expr_match(sp, cx.expr_self(sp), arms);
+            let compiler = cx.block(sp, vec![let_ty], Some(matcher));
+            let item = impl_compile(cx, sp, name, self_ty, &gen, type_expr, compiler);
+            push(Annotatable::Item(item));
         },
-        Item_::ItemStruct(ref def, ref gen) => {
-            if !matches!(repr, Some("packed") | Some("c") | Some("C")) {
+        Item_::ItemStruct(ref def, _) => {
+            if !is_packed && !is_c {
                 cx.span_err(sp, BAD_STRUCT);
                 return;
             }
+            let value = cx.ident_of("value");
+            let addr = cx.ident_of("addr");
+            let offset = cx.ident_of("offset");
             let mut fields = Vec::with_capacity(def.fields.len());
-            let mut names = Some(Vec::with_capacity(fields.len()));
-            let mut compiler = Vec::with_capacity(def.fields.len() + 1);
-            let types = gen.ty_params.iter().map(|param| cx.ty_ident(sp, param.ident)).collect();
-            let self_ty = cx.ty_path(cx.path_all(sp, false, vec![name], vec![], types, vec![]));
-            let self_type = type_expr(cx, sp, self_ty.clone(), false);
-            compiler.push(cx.stmt_let(sp, false, value, cx.expr_call(
-                sp,
-                cx.expr_path(jit_val_new),
-                vec![
-                    cx.expr_ident(sp, func),
-                    self_type
-                ]
-            )));
-            let lit_usize = LitIntType::UnsignedIntLit(UintTy::TyUs);
-            if def.fields.len() > 1 {
+            let mut names = Some(Vec::with_capacity(def.fields.len()));
+            let mut compiler = Vec::with_capacity(def.fields.len() + 4);
+            compiler.push(quote_stmt!(cx, let ty = $self_type).unwrap());
+            compiler.push(quote_stmt!(cx, let $value = ::jit::Val::new(func, ty)).unwrap());
+            if def.fields.len() > 0 {
+                compiler.push(quote_stmt!(cx, let $addr = func.insn_address_of($value)).unwrap());
+            }
+            // LibJIT lays out structs like C does, so packed structs need their
+            // offsets calculated by hand
+            if is_packed && def.fields.len() > 1 {
                 compiler.push(cx.stmt_let(sp, true, offset, cx.expr_lit(sp, Lit_::LitInt(0, lit_usize))));
+            } else if !is_packed && def.fields.len() > 0 {
+                compiler.push(quote_stmt!(cx, let mut fields = ty.fields()).unwrap());
             }
             for (index, field) in def.fields.iter().enumerate() {
                 let expr = type_expr(cx, sp, field.node.ty.clone(), false);
                 fields.push(expr);
-                let has_name = field.node.ident().is_some();
-                if has_name && names.is_some() {
-                    let ident = field.node.ident().unwrap();
-                    let expr = expand_stringify(cx, sp, &[TokenTree::TtToken(sp, Token::Ident(ident, IdentStyle::Plain))]);
-                    names.as_mut().unwrap().push(expr.make_expr().unwrap());
-                } else {
-                    names = None
-                }
-                let current_offset = if index == 0 {
+                let member = match field.node.ident() {
+                    Some(ident) => {
+                        if let Some(ref mut names) = names {
+                            names.push(ident_str(cx, sp, ident));
+                        }
+                        cx.expr_field_access(sp, cx.expr_self(sp), ident)
+                    },
+                    None => {
+                        names = None;
+                        cx.expr_tup_field_access(sp, cx.expr_self(sp), index)
+                    }
+                };
+                let current_offset = if !is_packed {
+                    quote_expr!(cx, fields.next().unwrap().get_offset())
+                } else if index == 0 {
                     cx.expr_lit(sp, Lit_::LitInt(0, lit_usize))
                 } else {
                     cx.expr_ident(sp, offset)
                 };
-                let name = field.node.ident().unwrap();
-                compiler.push(quote_stmt!(cx, func.insn_store_relative(value, $current_offset, self.$name.compile(func))).unwrap());
+                compiler.push(quote_stmt!(cx, func.insn_store_relative($addr, $current_offset, $member.compile(func))).unwrap());
                 let size_of = cx.expr_path(cx.path_all(sp, false, vec![cx.ident_of("std"), cx.ident_of("mem"), cx.ident_of("size_of")], vec![], vec![field.node.ty.clone()], vec![]));
-                if def.fields.len() > 1 && index < def.fields.len() - 1 {
-                    compiler.push(quote_stmt!(cx, offset += $size_of()).unwrap());
+                if is_packed && def.fields.len() > 1 && index < def.fields.len() - 1 {
+                    compiler.push(quote_stmt!(cx, $offset += $size_of()).unwrap());
                 }
             }
             let fields = cx.expr_mut_addr_of(sp, cx.expr_vec(sp, fields));
             let mut type_expr = cx.expr_call(sp, cx.expr_path(new_struct), vec![fields]);
-            if let Some(names) = names {
-                let names = cx.expr_vec(sp, names);
-                type_expr = quote_expr!(cx, {
-                    let mut ty: Type = $type_expr;
-                    ty.set_names(&$names);
-                    ty.into()
-                })
-            }
-            let item = cx.item(sp, name, vec![], Item_::ItemImpl(
-                Unsafety::Normal,
-                ImplPolarity::Positive,
-                Generics {
-                    lifetimes: vec![ LifetimeDef {lifetime: jit_life, bounds: vec![]}],
-                    ty_params: gen.ty_params.clone(),
-                    where_clause: WhereClause {
-                        id: DUMMY_NODE_ID,
-                        predicates: gen.ty_params.iter()
-                            .map(|param| WherePredicate::BoundPredicate(
-                                WhereBoundPredicate {
-                                    span: sp,
-                                    bound_lifetimes: vec![],
-                                    bounded_ty: cx.ty_ident(sp, param.ident),
-                                    bounds: OwnedSlice::from_vec(vec![
-                                        TyParamBound::TraitTyParamBound(
-                                            cx.poly_trait_ref(sp, jit_compile.clone()),
-                                            TraitBoundModifier::None
-                                        ),
-                                    ])
-                                }
-                            ))
-                            .collect()
-                    }
-                },
-                Some(cx.trait_ref(jit_compile)),
-                self_ty,
-                vec![
-                    P(ImplItem {
-                        attrs: vec![],
-                        id: DUMMY_NODE_ID,
-                        span: sp,
-                        ident: cx.ident_of("get_type"),
-                        vis: Visibility::Inherited,
-                        node: ImplItem_::MethodImplItem(
-                            MethodSig {
-                                constness: Constness::NotConst,
-                                unsafety: Unsafety::Normal,
-                                abi: Abi::Rust,
-                                explicit_self: respan(sp, ExplicitSelf_::SelfStatic),
-                                decl: cx.fn_decl(vec![], cx.ty_path(jit_cow_type)),
-                                generics: empty_generics(),
-                            },
-                            cx.block_expr(type_expr)
-                        )
-                    }),
-                    P(ImplItem {
-                        attrs: vec![],
-                        id: DUMMY_NODE_ID,
-                        span: sp,
-                        ident: cx.ident_of("compile"),
-                        vis: Visibility::Inherited,
-                        node: ImplItem_::MethodImplItem(
-                            MethodSig {
-                                constness: Constness::NotConst,
-                                unsafety: Unsafety::Normal,
-                                abi: Abi::Rust,
-                                explicit_self: respan(
-                                    sp,
-                                    ExplicitSelf_::SelfValue(cx.ident_of("b"))),
-                                decl: cx.fn_decl(
-                                    vec![
-                                        Arg::new_self(sp, Mutability::MutImmutable,
-                                                      cx.ident_of("self")),
-                                        cx.arg(sp, func, cx.ty_rptr(sp, cx.ty_path(jit_func),
-                                                                    None, Mutability::MutImmutable))],
-                                    jit_value),
-                                generics: empty_generics(),
-                            },
-                            cx.block(sp, compiler, Some(cx.expr_ident(sp, value))))
+            match names {
+                Some(ref names) if names.len() > 0 => {
+                    let names = cx.expr_vec(sp, names.clone());
+                    type_expr = quote_expr!(cx, {
+                        let mut ty: ::jit::Type = $type_expr;
+                        ty.set_names(&$names);
+                        ty
                     })
-                ]
-            ));
+                },
+                _ => ()
+            }
+            let type_expr = quote_expr!(cx, $type_expr.into());
+            let type_expr = cache_type_expr(cx, &self_ty, &gen, type_expr);
+            let compiler = cx.block(sp, compiler, Some(cx.expr_ident(sp, value)));
+            let item = impl_compile(cx, sp, name, self_ty, &gen, type_expr, compiler);
             push(Annotatable::Item(item));
         },
-        _ => {
-            cx.span_err(sp, BAD_ITEM);
-            return;
-        }
+        _ => unreachable!()
     }
 }
 macro_rules! error(
@@ -312,7 +375,10 @@ macro_rules! error(
 );
 
 struct ExprCtxt {
-    sp: Span
+    sp: Span,
+    /// If the expression is inside a loop, so `_jit_loop` is bound to the
+    /// innermost loop's context
+    in_loop: bool
 }
 
 fn compile_expr(cx: &mut ExtCtxt, ctx: &ExprCtxt, expr: P<Expr>) -> P<Expr> {
@@ -380,16 +446,43 @@ fn compile_expr(cx: &mut ExtCtxt, ctx: &ExprCtxt, expr: P<Expr>) -> P<Expr> {
             let value = compile_expr(cx, ctx, value.clone());
             quote_expr!(cx, func.insn_return($value))
         },
+        // the body's closure binds `_jit_loop` without hygiene, so a `break`
+        // or `continue` in a closure nested inside it uses the innermost loop
         Expr_::ExprLoop(ref block, _) => {
+            let body_ctx = ExprCtxt { sp: ctx.sp, in_loop: true };
             let block = cx.expr_block(block.clone());
-            let block = compile_expr(cx, ctx, block);
-            quote_expr!(cx, func.insn_loop(|| $block))
+            let block = compile_expr(cx, &body_ctx, block);
+            quote_expr!(cx, func.insn_loop_with(|_jit_loop| $block))
         },
         Expr_::ExprWhile(ref cond, ref block, _) => {
             let cond = compile_expr(cx, ctx, cond.clone());
+            let body_ctx = ExprCtxt { sp: ctx.sp, in_loop: true };
             let block = cx.expr_block(block.clone());
-            let block = compile_expr(cx, ctx, block);
-            quote_expr!(cx, func.insn_while(|| $cond, || $block))
+            let block = compile_expr(cx, &body_ctx, block);
+            quote_expr!(cx, func.insn_while_with(|| $cond, |_jit_loop| $block))
+        },
+        Expr_::ExprBreak(None) | Expr_::ExprAgain(None) if !ctx.in_loop => {
+            cx.span_err(sp, "break and continue should be inside a loop");
+            quote_expr!(cx, ())
+        },
+        Expr_::ExprBreak(None) => quote_expr!(cx, _jit_loop.break_()),
+        Expr_::ExprAgain(None) => quote_expr!(cx, _jit_loop.continue_()),
+        Expr_::ExprBlock(ref block) => {
+            let mut stmts = Vec::new();
+            for stmt in &block.stmts {
+                match stmt.node {
+                    Stmt_::StmtExpr(ref expr, _) | Stmt_::StmtSemi(ref expr, _) => {
+                        let compiled = compile_expr(cx, ctx, expr.clone());
+                        stmts.push(cx.stmt_expr(compiled));
+                    },
+                    _ => cx.span_err(stmt.span, "Only expressions are allowed in nested blocks")
+                }
+            }
+            if let Some(ref value) = block.expr {
+                let compiled = compile_expr(cx, ctx, value.clone());
+                stmts.push(cx.stmt_expr(compiled));
+            }
+            cx.expr_block(cx.block_all(sp, stmts, None))
         },
         Expr_::ExprAddrOf(_, ref value) => {
             let value = compile_expr(cx, ctx, value.clone());
@@ -432,7 +525,8 @@ fn compile_expr(cx: &mut ExtCtxt, ctx: &ExprCtxt, expr: P<Expr>) -> P<Expr> {
 fn expand_jit(cx: &mut ExtCtxt, sp: Span, tt: &[TokenTree]) -> Box<MacResult> {
     if let Some(exprs) = get_exprs_from_tts(cx, sp, tt) {
         let ctx = ExprCtxt {
-            sp: sp
+            sp: sp,
+            in_loop: false
         };
         let mut stmts = Vec::new();
         if let Expr_::ExprClosure(_, ref decl, ref block) = exprs[1].node {
@@ -601,12 +695,12 @@ macro_rules! jit(
     ($func:ident, jump_table($value:expr,
         $($label:ident),+
     )) => (
-    let ($($label),+) = {
-        $(let $label:Label = Label::new($func);)+
-        $func.insn_jump_table($value, [
-            $($label),+
-        ].as_mut_slice());
-        ($($label),+)
+    let ($(mut $label,)+) = {
+        $(let $label = Label::new(&$func);)+
+        $func.insn_jump_table($value, &mut [
+            $($label.clone()),+
+        ]);
+        ($($label,)+)
     });
 );
 #[macro_export]
@@ -628,11 +722,8 @@ macro_rules! jit_func(
         let func = UncompiledFunction::new($ctx, &get::<fn($($ty),+) -> $ret>());
         {
             let $name = &func;
-            let mut i = 0;
-            $(let $arg = {
-                i += 1;
-                &$name[i - 1]
-            };)*
+            let mut params = $name.params();
+            $(let $arg = params.next().unwrap();)*
             $($st;)+
         };
         func.compile().with(|comp: extern fn(($($ty),+)) -> $ret| {
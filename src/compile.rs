@@ -1,11 +1,12 @@
 use raw::*;
 use function::UncompiledFunction;
 use function::Abi::CDecl;
-use types::get;
+use types::{cache_shape, cache_type, get, TypeKind};
 use libc::c_long;
-use types::{consts, CowType, Type};
+use types::{consts, CowType, StructLayout, Type};
 use util::from_ptr;
 use value::Val;
+use std::any::TypeId;
 use std::ffi::CStr;
 use std::mem;
 /// A type that can be compiled into a LibJIT representation
@@ -80,9 +81,81 @@ impl<'a> Compile<'a> for &'a str {
     #[inline(always)]
     fn get_type() -> CowType<'a> {
         use std::raw::Slice;
-        let ty = Type::new_struct(&mut [&get::<&'static u8>(), &get::<usize>()]);
-        unsafe {
-            jit_type_set_size_and_alignment((&ty).into(), mem::size_of::<Slice<u8>>() as i64, mem::align_of::<Slice<u8>>() as i64);
+        cache_type(TypeId::of::<&'static str>(), || {
+            let data_t = get::<&'static u8>();
+            let len_t = get::<usize>();
+            StructLayout::new()
+                .field("data", &data_t)
+                .field("len", &len_t)
+                .build_for::<Slice<u8>>()
+                .unwrap()
+        })
+    }
+}
+impl<'a, T> Compile<'a> for &'a [T] where T:Compile<'a> {
+    #[inline(always)]
+    fn compile(self, func:&UncompiledFunction<'a>) -> &'a Val {
+        let ty = <&'a [T] as Compile<'a>>::get_type();
+        let slice = Val::new(func, &ty);
+        let addr = func.insn_address_of(slice);
+        let mut fields = ty.fields();
+        let data_f = fields.next().unwrap();
+        let len_f = fields.next().unwrap();
+        let data = unsafe {
+            from_ptr(jit_value_create_nint_constant(
+                func.into(),
+                data_f.get_type().into(),
+                self.as_ptr() as c_long
+            ))
+        };
+        func.insn_store_relative(addr, data_f.get_offset(), data);
+        func.insn_store_relative(addr, len_f.get_offset(), func.insn_of(self.len()));
+        slice
+    }
+    #[inline(always)]
+    fn get_type() -> CowType<'a> {
+        use std::raw::Slice;
+        let data_t = Type::new_pointer(&get::<T>());
+        let len_t = get::<usize>();
+        StructLayout::new()
+            .field("data", &data_t)
+            .field("len", &len_t)
+            .build_for::<Slice<T>>()
+            .unwrap()
+            .into()
+    }
+}
+impl<'a, T> Compile<'a> for Option<T> where T:Compile<'a> {
+    #[inline(always)]
+    fn compile(self, func:&UncompiledFunction<'a>) -> &'a Val {
+        match self {
+            Some(value) => func.insn_make_some(func.insn_of(value)),
+            None => func.insn_make_none(&get::<T>())
+        }
+    }
+    #[inline(always)]
+    fn get_type() -> CowType<'a> {
+        let ty = Type::new_option(&get::<T>());
+        if ty.get_size() != mem::size_of::<Option<T>>() {
+            panic!("Option of {:?} can't be compiled because its layout doesn't match Rust's", get::<T>())
+        }
+        ty.into()
+    }
+}
+impl<'a, T, E> Compile<'a> for Result<T, E> where T:Compile<'a>, E:Compile<'a> {
+    #[inline(always)]
+    fn compile(self, func:&UncompiledFunction<'a>) -> &'a Val {
+        match self {
+            Ok(value) => func.insn_make_ok(func.insn_of(value), &get::<E>()),
+            Err(error) => func.insn_make_err(&get::<T>(), func.insn_of(error))
+        }
+    }
+    #[inline(always)]
+    fn get_type() -> CowType<'a> {
+        let ty = Type::new_result(&get::<T>(), &get::<E>());
+        if ty.get_size() != mem::size_of::<Result<T, E>>() {
+            panic!("Result of {:?} and {:?} can't be compiled because its layout doesn't match Rust's",
+                   get::<T>(), get::<E>())
         }
         ty.into()
     }
@@ -95,7 +168,7 @@ impl<'a> Compile<'a> for &'a CStr {
     }
     #[inline(always)]
     fn get_type() -> CowType<'a> {
-        Type::new_pointer(consts::get_sys_char()).into()
+        cache_type(TypeId::of::<&'static CStr>(), || Type::new_pointer(consts::get_sys_char()))
     }
 }
 compile_tuple!(A, B => a, b);
@@ -107,3 +180,7 @@ compile_func!(fn(A) -> R, fn(A) -> R, extern fn(A) -> R);
 compile_func!(fn(A, B) -> R, fn(A, B) -> R, extern fn(A, B) -> R);
 compile_func!(fn(A, B, C) -> R, fn(A, B, C) -> R, extern fn(A, B, C) -> R);
 compile_func!(fn(A, B, C, D) -> R, fn(A, B, C, D) -> R, extern fn(A, B, C, D) -> R);
+compile_func!(fn(A, B, C, D, E) -> R, fn(A, B, C, D, E) -> R, extern fn(A, B, C, D, E) -> R);
+compile_func!(fn(A, B, C, D, E, F) -> R, fn(A, B, C, D, E, F) -> R, extern fn(A, B, C, D, E, F) -> R);
+compile_func!(fn(A, B, C, D, E, F, G) -> R, fn(A, B, C, D, E, F, G) -> R, extern fn(A, B, C, D, E, F, G) -> R);
+compile_func!(fn(A, B, C, D, E, F, G, H) -> R, fn(A, B, C, D, E, F, G, H) -> R, extern fn(A, B, C, D, E, F, G, H) -> R);
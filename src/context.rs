@@ -1,7 +1,15 @@
 use raw::*;
 use alloc::oom;
-use function::Func;
+use compile::Compile;
+use function::{Func, NativeFn};
+use symbol::{self, SymbolTable, SYMBOL_META};
+use types::{get, Ty, Type};
 use util::{from_ptr, from_ptr_opt};
+use libc::c_char;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::hash::{Hash, Hasher, SipHasher};
 use std::marker::PhantomData;
 use std::{mem, ptr};
 use std::ops::{Index, IndexMut};
@@ -29,15 +37,21 @@ use std::iter::IntoIterator;
 /// assert_eq!(ctx[0], 42);
 /// assert_eq!(ctx[1], 21);
 /// ```
+/// The index `0x73796d73` is reserved for the context's symbol table, so
+/// indexing it panics.
 pub struct Context<T = ()> {
     _context: jit_context_t,
+    types: RefCell<HashMap<u64, Vec<Type>>>,
     marker: PhantomData<T>
 }
-native_ref!(Context<T>, _context: jit_context_t, marker = PhantomData);
+native_ref!(Context<T>, _context: jit_context_t, types = RefCell::new(HashMap::new()), marker = PhantomData);
 
 impl<T = ()> Index<i32> for Context<T> {
     type Output = T;
     fn index(&self, index: i32) -> &T {
+        if index == SYMBOL_META {
+            panic!("Index {} on Context is reserved for the symbol table", index)
+        }
         unsafe {
             let meta = jit_context_get_meta(self.into(), index);
             if meta.is_null() {
@@ -49,6 +63,9 @@ impl<T = ()> Index<i32> for Context<T> {
 }
 impl<T = ()> IndexMut<i32> for Context<T> {
     fn index_mut(&mut self, index: i32) -> &mut T {
+        if index == SYMBOL_META {
+            panic!("Index {} on Context is reserved for the symbol table", index)
+        }
         unsafe {
             let meta = jit_context_get_meta(self.into(), index);
             if meta.is_null() {
@@ -72,6 +89,87 @@ impl<T = ()> Context<T> {
             from_ptr(jit_context_create())
         }
     }
+    /// Get the canonical type descriptor that is structurally equal to `ty`,
+    /// so types with the same shape are interned as the same `Ty`
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let ctx = Context::<()>::new();
+    /// let f64_t = get::<f64>();
+    /// let a = ctx.intern(&Type::new_struct(&mut [&f64_t, &f64_t]));
+    /// let b = ctx.intern(&Type::new_struct(&mut [&f64_t, &f64_t]));
+    /// assert_eq!(a, b);
+    /// ```
+    pub fn intern(&self, ty: &Ty) -> &Ty {
+        let mut hasher = SipHasher::new();
+        ty.hash(&mut hasher);
+        let mut types = self.types.borrow_mut();
+        let bucket = types.entry(hasher.finish()).or_insert_with(Vec::new);
+        let index = bucket.iter().position(|other| ty.structurally_eq(other));
+        let index = index.unwrap_or_else(|| {
+            bucket.push(ty.to_owned());
+            bucket.len() - 1
+        });
+        let interned:&Ty = &bucket[index];
+        // interned types are never freed before the context is
+        unsafe { mem::transmute(interned) }
+    }
+    #[inline]
+    /// Get the canonical type descriptor for the Rust type `U`, so repeated
+    /// calls return the same `Ty`
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let ctx = Context::<()>::new();
+    /// assert_eq!(ctx.get_type::<(f64, f64)>(), ctx.get_type::<(f64, f64)>());
+    /// ```
+    pub fn get_type<'a, U>(&'a self) -> &'a Ty where U:Compile<'a> {
+        self.intern(&get::<U>())
+    }
+    /// Register a Rust function under `name` so JIT code can call it with
+    /// `insn_call_symbol`, and so ELF binaries added to this context can
+    /// resolve it
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// extern fn triple(x: i32) -> i32 {
+    ///     x * 3
+    /// }
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.register("triple", triple as extern fn(i32) -> i32);
+    /// assert!(ctx.symbols().contains("triple"));
+    /// ```
+    pub fn register<F>(&mut self, name: &str, func: F) where F:NativeFn<'static> {
+        let ptr = func.as_ptr();
+        self.symbol_table().insert(name, func);
+        unsafe {
+            let c_name = CString::new(name.as_bytes()).unwrap();
+            if jit_readelf_register_symbol(self.into(), c_name.as_bytes().as_ptr() as *const c_char, ptr, 0) == 0 {
+                oom()
+            }
+        }
+    }
+    #[inline]
+    /// Get the functions that have been registered on this context
+    pub fn symbols(&self) -> &SymbolTable {
+        unsafe { &*self.symbol_meta() }
+    }
+    #[inline]
+    fn symbol_table(&mut self) -> &mut SymbolTable {
+        unsafe { &mut *self.symbol_meta() }
+    }
+    /// Get a pointer to the symbol table, making it if there isn't one yet
+    fn symbol_meta(&self) -> *mut SymbolTable {
+        unsafe {
+            if symbol::table(self.into()).is_none() {
+                let table = Box::new(SymbolTable::new());
+                if jit_context_set_meta(self.into(), SYMBOL_META, mem::transmute(table), Some(::free_data::<SymbolTable>)) == 0 {
+                    oom()
+                }
+            }
+            jit_context_get_meta(self.into(), SYMBOL_META) as *mut SymbolTable
+        }
+    }
     /// Iterate through the functions contained inside this context
     pub fn functions(&self) -> Functions {
         Functions {
@@ -2,7 +2,7 @@ use raw::*;
 use context::Context;
 use function::CompiledFunction;
 use util::from_ptr;
-use libc::{c_uint, c_char};
+use libc::{c_int, c_uint, c_char};
 use std::ffi::{self, CString};
 use std::{fmt, str};
 use std::marker::PhantomData;
@@ -126,9 +126,27 @@ impl ReadElf {
         }
     }
     #[inline]
-    pub fn add_to_context(&self, ctx:&Context) {
+    /// Add this ELF binary to the context given, so its symbols can be
+    /// resolved along with the functions registered with `Context::register`
+    ///
+    /// The context closes the binary when it is destroyed, so look up any
+    /// symbols needed with `get_symbol` before adding it.
+    ///
+    /// This takes the binary by value, where it used to borrow it, so calls
+    /// that keep using the binary after adding it no longer compile. Before,
+    /// dropping the binary after adding it closed it twice.
+    pub fn add_to_context<T>(self, ctx:&Context<T>) {
         unsafe {
-            jit_readelf_add_to_context(self.into(), ctx.into())
+            jit_readelf_add_to_context((&self).into(), ctx.into());
+            mem::forget(self);
+        }
+    }
+    #[inline]
+    /// Resolve the symbols needed by the ELF binaries added to the context
+    /// given, returning `false` if any are missing
+    pub fn resolve_all<T>(ctx:&Context<T>, print_failures: bool) -> bool {
+        unsafe {
+            jit_readelf_resolve_all(ctx.into(), print_failures as c_int) != 0
         }
     }
     #[inline]
@@ -1,10 +1,12 @@
 use raw::*;
+use alloc::oom;
 use context::Context;
 use compile::Compile;
 use label::Label;
-use types::Ty;
+use symbol::{self, SymbolError};
+use types::{consts, get, Field, Ty, Type, TypeKind};
 use insn::Block;
-use value::Val;
+use value::{Constant, Val, Var};
 use util::{self, from_ptr, from_ptr_opt, from_ptr_oom};
 use libc::{
     c_char,
@@ -12,25 +14,94 @@ use libc::{
     c_uint,
     c_void
 };
+use std::cell::Cell;
 use std::default::Default;
+use std::error::Error;
 use std::fmt;
 use std::ops::{Deref, DerefMut, Index};
 use std::{mem, ptr};
 use std::ffi::CString;
 use std::marker::PhantomData;
+use std::sync::{Once, ONCE_INIT};
+/// Check if values of the type given can be converted with `jit_insn_convert`
+fn is_convertible(ty: &Ty) -> bool {
+    let ty = ty.remove_tags();
+    (ty.is_primitive() || ty.is_pointer()) && ty.get_kind() != TypeKind::Void
+}
+/// Check if `func` is `ancestor` or is nested inside it
+fn is_nested_in(func: &Func, ancestor: &Func) -> bool {
+    let mut current = Some(func);
+    while let Some(func) = current {
+        if func == ancestor {
+            return true
+        }
+        current = func.get_nested_parent();
+    }
+    false
+}
+/// Panic if the arguments given don't match the parameters of `signature`
+fn check_args(name: &str, signature: &Ty, args: &[&Val]) {
+    if !signature.is_signature() {
+        panic!("Bad signature for {} - expected signature, got {:?}", name, signature)
+    }
+    let num_sig_args = signature.params().count();
+    if args.len() != num_sig_args {
+        panic!("Bad arguments to {} - expected {}, got {}", name, num_sig_args, args.len());
+    }
+    for (index, (arg, param)) in args.iter().zip(signature.params()).enumerate() {
+        let ty = arg.get_type();
+        if !ty.structurally_eq(param) {
+            panic!("Bad argument #{} to {} - expected {:?}, got {:?}", index, name, param, ty);
+        }
+    }
+}
+/// Check if values of the integer type given are unsigned
+pub fn is_unsigned(ty: &Ty) -> bool {
+    let kind = ty.remove_tags().get_kind();
+    kind == TypeKind::UByte || kind == TypeKind::UShort || kind == TypeKind::UInt
+        || kind == TypeKind::ULong || kind == TypeKind::NUInt
+}
+/// Get the constant of the type given for a case of `insn_switch`, panicking
+/// if it doesn't fit
+fn switch_case(ty: &Ty, case: i64) -> Constant {
+    let constant = if is_unsigned(ty) {
+        Constant::ULong(case as u64)
+    } else {
+        Constant::Long(case)
+    };
+    match constant.convert(ty, true) {
+        Some(constant) => constant,
+        None => panic!("Case {} given to insn_switch doesn't fit in {:?}", case, ty)
+    }
+}
+/// The meta type used to flag that a function folds constants
+const FOLD_META: c_int = 0x666f6c64;
+/// The meta type used to hold the labels around each cold path of a function,
+/// which are moved to its end when it is compiled
+const COLD_META: c_int = 0x636f6c64;
+/// The labels at the start of a cold path and after its end
+type ColdPaths = Vec<(jit_label_t, jit_label_t)>;
 /// A platform's application binary interface
 ///
 /// This describes how the function should be called
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Abi {
     /// The C application binary interface
     CDecl,
     /// The C application binary interface with variable arguments
     VarArg,
-    /// A Windows application binary interface*-+
+    /// The Windows application binary interface where the function called
+    /// pops its arguments
+    ///
+    /// This only differs from `CDecl` on 32-bit x86, so on x86-64 Linux it is
+    /// a no-op.
     StdCall,
-    /// A Windows application binary interface
+    /// The Windows application binary interface that passes the first
+    /// arguments in registers
+    ///
+    /// This only differs from `CDecl` on 32-bit x86, so on x86-64 Linux it is
+    /// a no-op.
     FastCall
 }
 impl Default for Abi {
@@ -53,6 +124,107 @@ pub mod flags {
         }
     );
 }
+/// An exception built into LibJIT, which is thrown by instructions such as
+/// `insn_check_null` and `insn_index_checked`
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuiltinException {
+    /// An arithmetic operation overflowed
+    Overflow = 0,
+    /// An arithmetic operation failed
+    Arithmetic = -1,
+    /// A number was divided by zero
+    DivisionByZero = -2,
+    /// An error occurred while compiling a function
+    CompileError = -3,
+    /// There wasn't enough memory to continue
+    OutOfMemory = -4,
+    /// A null pointer was dereferenced
+    NullReference = -5,
+    /// A null function pointer was called
+    NullFunction = -6,
+    /// A nested function was called from a non-nested context
+    CalledNested = -7,
+    /// An array index was out of bounds
+    OutOfBounds = -8,
+    /// A branch was made to a label that was never defined
+    UndefinedLabel = -9
+}
+/// The objects thrown for builtin exceptions while a function is applied,
+/// indexed by the negated exception code
+static BUILTINS: [BuiltinException; 10] = [
+    BuiltinException::Overflow,
+    BuiltinException::Arithmetic,
+    BuiltinException::DivisionByZero,
+    BuiltinException::CompileError,
+    BuiltinException::OutOfMemory,
+    BuiltinException::NullReference,
+    BuiltinException::NullFunction,
+    BuiltinException::CalledNested,
+    BuiltinException::OutOfBounds,
+    BuiltinException::UndefinedLabel
+];
+/// Makes sure the exception handler is only installed once per process
+static INSTALL_HANDLER: Once = ONCE_INIT;
+/// The handler that was installed before ours, which handles builtin
+/// exceptions thrown outside `apply`
+static mut OUTER_HANDLER: jit_exception_func = None;
+// the handler is shared by every thread, so whether a builtin exception
+// should become an object is tracked for each thread
+thread_local!(static APPLYING: Cell<bool> = Cell::new(false));
+/// Get the object to throw for the builtin exception with the code given
+extern fn builtin_object(exception: c_int) -> *mut c_void {
+    if APPLYING.with(|applying| applying.get()) {
+        match BUILTINS.get(-(exception as isize) as usize) {
+            Some(builtin) => builtin as *const BuiltinException as *mut c_void,
+            None => ptr::null_mut()
+        }
+    } else {
+        match unsafe { OUTER_HANDLER } {
+            Some(handler) => handler(exception),
+            None => ptr::null_mut()
+        }
+    }
+}
+/// An error from trying to make a tail call
+///
+/// A call that gets none of these errors is still only made in place of the
+/// caller when a function calls itself. A tail call to any other function is
+/// made by LibJIT as a normal call followed by a return, so it uses a new frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TailCallError {
+    /// The signature of the function called doesn't match the caller's
+    Signature,
+    /// The number of arguments given doesn't match the function's signature,
+    /// with the number of parameters first
+    ArgCount(usize, usize),
+    /// The caller or the function called is nested, so the frame can't be reused
+    Nested,
+    /// The argument with the index given is addressable, so its address could
+    /// be passed into the frame the call reuses
+    Addressable(usize)
+}
+impl fmt::Display for TailCallError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TailCallError::ArgCount(params, args) =>
+                write!(fmt, "{}: expected {} but got {}", self.description(), params, args),
+            TailCallError::Addressable(index) =>
+                write!(fmt, "{}: argument {}", self.description(), index),
+            _ => write!(fmt, "{}", self.description())
+        }
+    }
+}
+impl Error for TailCallError {
+    fn description(&self) -> &'static str {
+        match *self {
+            TailCallError::Signature => "Function called has a different signature to the caller",
+            TailCallError::ArgCount(_, _) => "Wrong number of arguments",
+            TailCallError::Nested => "Nested functions can't make or receive tail calls",
+            TailCallError::Addressable(_) => "Addressable values can't be passed to tail calls"
+        }
+    }
+}
 /// A function
 pub struct Func(PhantomData<[()]>);
 native_ref!(&Func = jit_function_t);
@@ -65,6 +237,10 @@ impl Func {
     pub fn get_signature(&self) -> &Ty {
         unsafe { from_ptr(jit_function_get_signature(self.into())) }
     }
+    /// Get the function this function is nested inside, if it is nested
+    pub fn get_nested_parent(&self) -> Option<&Func> {
+        unsafe { from_ptr_opt(jit_function_get_nested_parent(self.into())) }
+    }
 }
 /// A function which has already been compiled from an `UncompiledFunction`, so it can
 /// be called but not added to.
@@ -102,6 +278,37 @@ impl<'a> CompiledFunction<'a> {
             mem::transmute(jit_function_to_closure(self._func))
         })
     }
+    /// Call the function with pointers to its arguments, writing its return
+    /// value to `ret`, or give the builtin exception it threw instead
+    ///
+    /// This is unsafe because the pointers must point to values of the types
+    /// in the function's signature. This panics if the function throws a
+    /// value that isn't a builtin exception.
+    ///
+    /// The first call installs an exception handler for the whole process,
+    /// which passes builtin exceptions thrown outside `apply` on to the
+    /// handler it replaced, so this is safe to call from several threads.
+    pub unsafe fn apply(&self, args: &mut [*mut c_void], ret: *mut c_void) -> Result<(), BuiltinException> {
+        let num_params = self.get_signature().params().count();
+        if args.len() != num_params {
+            panic!("Bad arguments to apply - expected {}, got {}", num_params, args.len());
+        }
+        INSTALL_HANDLER.call_once(|| {
+            OUTER_HANDLER = jit_exception_set_handler(Some(builtin_object));
+        });
+        let was_applying = APPLYING.with(|applying| applying.get());
+        APPLYING.with(|applying| applying.set(true));
+        let returned = jit_function_apply(self._func, args.as_mut_ptr(), ret) != 0;
+        APPLYING.with(|applying| applying.set(was_applying));
+        if returned {
+            return Ok(())
+        }
+        let thrown = jit_exception_get_last_and_clear() as *const BuiltinException;
+        match BUILTINS.iter().find(|&builtin| builtin as *const BuiltinException == thrown) {
+            Some(&builtin) => Err(builtin),
+            None => panic!("Function threw a value that isn't a builtin exception")
+        }
+    }
 }
 
 macro_rules! expect(
@@ -132,6 +339,33 @@ macro_rules! expect(
             }
         }
     );
+    ($name:ident, $value:expr, $ty:expr, convertible) => (
+        if cfg!(not(ndebug)) {
+            let from_t = $value.get_type();
+            let to_t = $ty;
+            if !is_convertible(from_t) {
+                panic!("Value given to {} should be numeric or pointer, got {:?}", stringify!($name), from_t);
+            } else if !is_convertible(to_t) {
+                panic!("Type given to {} should be numeric or pointer, got {:?}", stringify!($name), to_t);
+            }
+        }
+    );
+    ($name:ident, $value:expr, bool) => (
+        if cfg!(not(ndebug)) {
+            let ty = $value.get_type();
+            if !is_convertible(ty) {
+                panic!("Value given to {} should be numeric or pointer to convert to bool, got {:?}", stringify!($name), ty);
+            }
+        }
+    );
+    ($name:ident, $value:expr, pointer) => (
+        if cfg!(not(ndebug)) {
+            let ty = $value.get_type();
+            if !ty.is_pointer() {
+                panic!("Value given to {} should be pointer, got {:?}", stringify!($name), ty);
+            }
+        }
+    );
     ($name:ident, $value:expr, int) => (
         if cfg!(not(ndebug)) {
             let ty = $value.get_type();
@@ -140,6 +374,14 @@ macro_rules! expect(
             }
         }
     );
+    ($name:ident, $value:expr, structure) => (
+        if cfg!(not(ndebug)) {
+            let ty = $value.get_type();
+            if !ty.is_struct() && !ty.is_union() {
+                panic!("Value given to {} should be struct or union, got {:?}", stringify!($name), ty);
+            }
+        }
+    );
     ($name:ident, $dest:expr, $source:expr, $size:expr) => (
         if cfg!(not(ndebug)) {
             let dest_t = $dest.get_type();
@@ -229,14 +471,204 @@ impl<'a> Index<usize> for UncompiledFunction<'a> {
     type Output = Val;
     /// Get the value that corresponds to a specified function parameter.
     fn index(&self, param: usize) -> &Val {
-        let ptr = unsafe { jit_value_get_param(self.into(), param as u32) };
-        if let Some(val) = from_ptr_opt(ptr) {
+        if let Some(val) = self.param(param) {
             val
         } else {
             panic!("Function {:?} has no parameter {}", self, param)
         }
     }
 }
+/// Iterates through the values of a function's parameters
+pub struct FuncParams<'a, 'b> where 'a:'b {
+    func: &'b UncompiledFunction<'a>,
+    index: usize,
+    length: usize
+}
+impl<'a, 'b> Iterator for FuncParams<'a, 'b> {
+    type Item = &'a Val;
+    fn next(&mut self) -> Option<&'a Val> {
+        if self.index < self.length {
+            self.index += 1;
+            self.func.param(self.index - 1)
+        } else {
+            None
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length - self.index, Some(self.length - self.index))
+    }
+}
+macro_rules! raw_insn(
+    ($func:expr, $insn:ident($($arg:expr),*)) => (
+        if $insn($func.into(), $($arg),*) == 0 {
+            oom()
+        }
+    )
+);
+/// Makes the instructions for a call by hand, for calling conventions that
+/// LibJIT doesn't handle itself, such as calling hand-written assembly stubs
+///
+/// These instructions bypass LibJIT's handling of registers and the stack,
+/// so getting them wrong corrupts the function's frame. Register numbers are
+/// the back end's own, not the processor's.
+pub struct RawCallBuilder<'a, 'b> where 'a:'b {
+    func: &'b UncompiledFunction<'a>
+}
+impl<'a, 'b> RawCallBuilder<'a, 'b> {
+    /// Mark the value given as arriving in the register given
+    pub fn incoming_reg(self, value: &'a Val, reg: i32) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_incoming_reg(value.into(), reg)) }
+        self
+    }
+    /// Mark the value given as arriving at the offset given in the frame
+    pub fn incoming_frame_posn(self, value: &'a Val, offset: isize) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_incoming_frame_posn(value.into(), offset as jit_nint)) }
+        self
+    }
+    /// Put the value given in the register given to pass it to the function
+    /// about to be called
+    pub fn outgoing_reg(self, value: &'a Val, reg: i32) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_outgoing_reg(value.into(), reg)) }
+        self
+    }
+    /// Put the value given at the offset given in the frame to pass it to the
+    /// function about to be called
+    pub fn outgoing_frame_posn(self, value: &'a Val, offset: isize) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_outgoing_frame_posn(value.into(), offset as jit_nint)) }
+        self
+    }
+    /// Mark the value given as being returned in the register given by the
+    /// function that was just called
+    pub fn return_reg(self, value: &'a Val, reg: i32) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_return_reg(value.into(), reg)) }
+        self
+    }
+    /// Pass the frame pointer needed by a nested function `nested_level` levels
+    /// up, where -1 is a child of this function, in the register given
+    pub fn setup_for_nested(self, nested_level: i32, reg: i32) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_setup_for_nested(nested_level, reg)) }
+        self
+    }
+    /// Push the value given onto the stack
+    pub fn push(self, value: &'a Val) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_push(value.into())) }
+        self
+    }
+    /// Push the value of the type given that the pointer given points to
+    /// onto the stack
+    pub fn push_ptr(self, ptr: &'a Val, ty: &Ty) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_push_ptr(ptr.into(), ty.into())) }
+        self
+    }
+    /// Store the value given at the offset given in the outgoing parameter area
+    pub fn set_param(self, value: &'a Val, offset: isize) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_set_param(value.into(), offset as jit_nint)) }
+        self
+    }
+    /// Store the value of the type given that the pointer given points to at
+    /// the offset given in the outgoing parameter area
+    pub fn set_param_ptr(self, ptr: &'a Val, ty: &Ty, offset: isize) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_set_param_ptr(ptr.into(), ty.into(), offset as jit_nint)) }
+        self
+    }
+    /// Push a pointer to the area the function about to be called should
+    /// write a struct return value to
+    pub fn push_return_area_ptr(self) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_push_return_area_ptr()) }
+        self
+    }
+    /// Pop the number of items given from the stack
+    pub fn pop_stack(self, num_items: isize) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_pop_stack(num_items as jit_nint)) }
+        self
+    }
+    /// Pop the number of items given from the stack later on, so the pops
+    /// from several calls can be combined
+    pub fn defer_pop_stack(self, num_items: isize) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_defer_pop_stack(num_items as jit_nint)) }
+        self
+    }
+    /// Pop the deferred items from the stack if there are more than the
+    /// number given
+    pub fn flush_defer_pop(self, num_items: isize) -> RawCallBuilder<'a, 'b> {
+        unsafe { raw_insn!(self.func, jit_insn_flush_defer_pop(num_items as jit_nint)) }
+        self
+    }
+}
+/// The labels of a loop being made, so its body can branch out of it or
+/// on to its next iteration
+pub struct LoopCtx<'a> {
+    func: UncompiledFunction<'a>,
+    next: Label<'a>,
+    end: Label<'a>
+}
+impl<'a> LoopCtx<'a> {
+    fn new(func: &UncompiledFunction<'a>) -> LoopCtx<'a> {
+        LoopCtx {
+            func: from_ptr(func.into()),
+            next: Label::new(func),
+            end: Label::new(func)
+        }
+    }
+    #[inline]
+    /// Make an instruction that branches out of the loop
+    pub fn break_(&self) {
+        self.func.insn_branch(&mut self.end.clone());
+    }
+    #[inline]
+    /// Make an instruction that branches on to the next iteration of the loop
+    pub fn continue_(&self) {
+        self.func.insn_branch(&mut self.next.clone());
+    }
+    #[inline]
+    /// Make an instruction that branches out of the loop if the value is true
+    pub fn break_if(&self, cond: &'a Val) {
+        self.func.insn_branch_if(cond, &mut self.end.clone());
+    }
+    #[inline]
+    /// Make an instruction that branches on to the next iteration of the loop
+    /// if the value is true
+    pub fn continue_if(&self, cond: &'a Val) {
+        self.func.insn_branch_if(cond, &mut self.next.clone());
+    }
+}
+/// A Rust function that can be called from JIT code
+///
+/// The signature is derived from the argument and return types, and the
+/// arguments are given as an array of the function's arity, so passing the
+/// wrong number of arguments is a compile-time error.
+pub trait NativeFn<'a>: Compile<'a> {
+    /// The values passed as arguments to the function
+    type Args;
+    /// Get a pointer to the function's code
+    fn as_ptr(self) -> *mut c_void;
+    /// View the arguments as a slice
+    fn args_slice(args: &mut Self::Args) -> &mut [&'a Val];
+}
+macro_rules! native_fn(
+    ($num:expr => $($arg:ident),*) => (
+        impl<'a, $($arg:Compile<'a>,)* R:Compile<'a>> NativeFn<'a> for extern fn($($arg),*) -> R {
+            type Args = [&'a Val; $num];
+            #[inline(always)]
+            fn as_ptr(self) -> *mut c_void {
+                unsafe { mem::transmute(self) }
+            }
+            #[inline(always)]
+            fn args_slice(args: &mut [&'a Val; $num]) -> &mut [&'a Val] {
+                args
+            }
+        }
+    )
+);
+native_fn!(0 => );
+native_fn!(1 => A);
+native_fn!(2 => A, B);
+native_fn!(3 => A, B, C);
+native_fn!(4 => A, B, C, D);
+native_fn!(5 => A, B, C, D, E);
+native_fn!(6 => A, B, C, D, E, F);
+native_fn!(7 => A, B, C, D, E, F, G);
+native_fn!(8 => A, B, C, D, E, F, G, H);
 impl<'a> UncompiledFunction<'a> {
     #[inline(always)]
     /// Create a new function block and associate it with a JIT context.
@@ -265,6 +697,50 @@ impl<'a> UncompiledFunction<'a> {
             me
         }
     }
+    /// Get the value of the parameter with the index given, or none if the
+    /// function doesn't have that many parameters
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    /// assert!(func.param(0).is_some());
+    /// assert!(func.param(1).is_none());
+    /// ```
+    pub fn param(&self, index: usize) -> Option<&'a Val> {
+        if index < self.get_signature().params().count() {
+            unsafe {
+                from_ptr_opt(jit_value_get_param(self.into(), index as c_uint))
+            }
+        } else {
+            None
+        }
+    }
+    /// Get the value of the parameter with the name given, where the names
+    /// are the ones set on the signature with `set_names`
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let mut sig = Type::new_signature(Abi::CDecl, &get::<f64>(), &mut [&get::<f64>(), &get::<f64>()]);
+    /// sig.set_names(&["x", "y"]);
+    /// let func = UncompiledFunction::new(&mut ctx, &sig);
+    /// assert_eq!(func.param_named("y"), func.param(1));
+    /// assert!(func.param_named("z").is_none());
+    /// ```
+    pub fn param_named(&self, name: &str) -> Option<&'a Val> {
+        self.get_signature().get_field(name)
+            .and_then(|field| self.param(field.index as usize))
+    }
+    #[inline]
+    /// Iterate through the values of this function's parameters
+    pub fn params<'b>(&'b self) -> FuncParams<'a, 'b> {
+        FuncParams {
+            func: self,
+            index: 0,
+            length: self.get_signature().params().count()
+        }
+    }
     #[inline(always)]
     /// Create a new function block and associate it with a JIT context.
     /// In addition, this function is nested inside the specified *parent*
@@ -275,26 +751,111 @@ impl<'a> UncompiledFunction<'a> {
     /// never be called by anyone except its parent and sibling functions.
     /// The front end is also responsible for ensuring that the nested function
     /// is compiled before its parent.
+    ///
+    /// Since the context can't be borrowed mutably while the parent made from
+    /// it is alive, `new_nested_in` is usually easier to use.
     pub fn new_nested<T>(context:&'a mut Context<T>, signature: &Ty,
                         parent: &'a UncompiledFunction<'a>) -> UncompiledFunction<'a> {
         unsafe {
-            let mut me:UncompiledFunction = from_ptr_oom(jit_function_create_nested(
-                context.into(),
-                signature.into(),
-                parent.into()
-            ));
-            me.owned = true;
-            if cfg!(any(test, bench, ndebug)) {
-                me.set_recompilable();
-                me.set_optimization_level(UncompiledFunction::get_max_optimization_level());
-            }
-            me
+            UncompiledFunction::create_nested(context.into(), signature, parent)
+        }
+    }
+    #[inline(always)]
+    /// Create a new function block in the same JIT context as the specified
+    /// *parent* function, which it is nested inside, so it is able to access
+    /// its parent's (and grandparent's) local variables with `import`.
+    ///
+    /// The front end is responsible for ensuring that the nested function can
+    /// never be called by anyone except its parent and sibling functions.
+    /// The front end is also responsible for ensuring that the nested function
+    /// is compiled before its parent.
+    ///
+    /// The parent stays borrowed while the nested function is alive, so make
+    /// the nested function in a block that ends before the parent is compiled.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    /// {
+    ///     let nested = UncompiledFunction::new_nested_in(&get::<fn() -> i32>(), &func);
+    ///     nested.insn_return(nested.insn_of(7i32));
+    ///     func.insn_return(func.insn_call_nested(None, &nested, &mut [], flags::CallFlags::NO_THROW));
+    ///     nested.compile();
+    /// }
+    /// func.compile();
+    /// ```
+    ///
+    /// The parent can't be dropped before the nested function:
+    ///
+    /// ```compile_fail
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let nested = {
+    ///     let parent = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    ///     UncompiledFunction::new_nested_in(&get::<fn() -> i32>(), &parent)
+    /// };
+    /// nested.insn_return(nested.insn_of(1i32));
+    /// ```
+    pub fn new_nested_in(signature: &Ty, parent: &'a UncompiledFunction<'a>) -> UncompiledFunction<'a> {
+        unsafe {
+            UncompiledFunction::create_nested(jit_function_get_context(parent.into()), signature, parent)
+        }
+    }
+    unsafe fn create_nested(context: jit_context_t, signature: &Ty,
+                            parent: &UncompiledFunction<'a>) -> UncompiledFunction<'a> {
+        let mut me:UncompiledFunction = from_ptr_oom(jit_function_create_nested(
+            context,
+            signature.into(),
+            parent.into()
+        ));
+        me.owned = true;
+        if cfg!(any(test, bench, ndebug)) {
+            me.set_recompilable();
+            me.set_optimization_level(UncompiledFunction::get_max_optimization_level());
         }
+        me
+    }
+    /// Get a value in this nested function that refers to a local variable
+    /// of its parent or another function it is nested inside
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let x = func.param(0).unwrap();
+    /// let nested = UncompiledFunction::new_nested_in(&get::<fn() -> i32>(), &func);
+    /// nested.insn_return(nested.import(x) * 2i32);
+    /// func.insn_return(func.insn_call_nested(Some("double"), &nested, &mut [], flags::CallFlags::NO_THROW));
+    /// ```
+    pub fn import(&self, value: &'a Val) -> &'a Val {
+        let owner = value.get_function();
+        if !self.get_nested_parent().map(|parent| is_nested_in(parent, &owner)).unwrap_or(false) {
+            panic!("Value given to import should belong to a function this is nested inside")
+        }
+        unsafe {
+            from_ptr_oom(jit_insn_import(self.into(), value.into()))
+        }
+    }
+    #[inline]
+    /// Make an instruction that calls a function nested inside this function
+    /// or one of the functions this is nested inside, so it can access their
+    /// local variables
+    pub fn insn_call_nested(&self, name: Option<&str>, func: &Func,
+                            args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
+        if !func.get_nested_parent().map(|parent| is_nested_in(self, parent)).unwrap_or(false) {
+            panic!("Function given to insn_call_nested should be nested inside this function or its parents")
+        }
+        self.insn_call(name, func, None, args, flags)
     }
     #[inline(always)]
     /// Make an instruction that converts the value to the type given
+    ///
+    /// If `overflow_check` is set, this throws an overflow exception when the
+    /// value doesn't fit in the type, otherwise it is truncated.
     pub fn insn_convert(&self, v: &'a Val,
                             t:&Ty, overflow_check:bool) -> &'a Val {
+        expect!(insn_convert, v, t, convertible);
         unsafe {
             from_ptr(jit_insn_convert(
                 self.into(),
@@ -304,6 +865,42 @@ impl<'a> UncompiledFunction<'a> {
             ))
         }
     }
+    #[inline]
+    /// Make an instruction that converts the value to the Rust type `T`,
+    /// truncating it like `as` does if it doesn't fit
+    ///
+    /// ```rust
+    /// # #[no_link] #[macro_use] extern crate jit_macros;
+    /// # extern crate jit;
+    /// # use jit::*;
+    /// # fn main() {
+    /// let mut ctx = Context::<()>::new();
+    /// jit_func!(&mut ctx, func, fn(x: f64) -> u8 {
+    ///     func.insn_return(func.insn_cast::<u8>(x));
+    /// }, assert_eq!(func(42.5), 42));
+    /// # }
+    /// ```
+    pub fn insn_cast<T>(&self, v: &'a Val) -> &'a Val where T:Compile<'a> {
+        self.insn_convert(v, &get::<T>(), false)
+    }
+    #[inline]
+    /// Make an instruction that converts the value to the Rust type `T`,
+    /// throwing an overflow exception if it doesn't fit
+    pub fn insn_cast_checked<T>(&self, v: &'a Val) -> &'a Val where T:Compile<'a> {
+        self.insn_convert(v, &get::<T>(), true)
+    }
+    #[inline]
+    /// Make an instruction that gives 1 if the value is non-zero, or 0 if it is
+    pub fn insn_to_bool(&self, v: &'a Val) -> &'a Val {
+        expect!(insn_to_bool, v, bool);
+        self.insn_unop(v, jit_insn_to_bool)
+    }
+    #[inline]
+    /// Make an instruction that gives 1 if the value is zero, or 0 if it isn't
+    pub fn insn_to_not_bool(&self, v: &'a Val) -> &'a Val {
+        expect!(insn_to_not_bool, v, bool);
+        self.insn_unop(v, jit_insn_to_not_bool)
+    }
     #[inline(always)]
     /// Make an instructional representation of a Rust value
     /// ```rust
@@ -330,6 +927,25 @@ impl<'a> UncompiledFunction<'a> {
             jit_insn_throw(self.into(), retval.into());
         }
     }
+    /// Make an instruction to throw one of LibJIT's builtin exceptions from the function
+    pub fn insn_throw_builtin(&self, exception: BuiltinException) {
+        let sig = Type::new_signature(Abi::CDecl, consts::get_void(), &mut [consts::get_int()]);
+        let builtin: unsafe extern fn(c_int) = jit_exception_builtin;
+        let code = self.insn_of(exception as i32);
+        self.insn_call_native_raw(Some("jit_exception_builtin"), unsafe { mem::transmute(builtin) },
+                              &sig, &mut [code], flags::CallFlags::NO_RETURN);
+    }
+    #[inline(always)]
+    /// Make an instruction that throws a null reference exception if the
+    /// pointer given is null
+    pub fn insn_check_null(&self, value: &'a Val) -> bool {
+        if cfg!(not(ndebug)) && !value.get_type().is_pointer() {
+            panic!("Value given to insn_check_null should be pointer, got {:?}", value.get_type());
+        }
+        unsafe {
+            jit_insn_check_null(self.into(), value.into()) != 0
+        }
+    }
     #[inline(always)]
     /// Make an instruction that will return from the function with the value given
     pub fn insn_return(&self, retval: &'a Val) {
@@ -347,7 +963,7 @@ impl<'a> UncompiledFunction<'a> {
     #[inline(always)]
     /// Make an instruction that multiplies the values
     pub fn insn_mul(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_mul)
+        self.insn_fold_binop(v1, v2, Constant::mul, jit_insn_mul)
     }
     #[inline(always)]
     /// Make an instruction that multiplies the values and throws upon overflow
@@ -360,7 +976,7 @@ impl<'a> UncompiledFunction<'a> {
     /// You can also just use `v1 + v2` in your code instead of running this method,
     /// `&Val` has the `Add` trait implemented so it can be added with normal operators.
     pub fn insn_add(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_add)
+        self.insn_fold_binop(v1, v2, Constant::add, jit_insn_add)
     }
     #[inline(always)]
     /// Make an instruction that adds the values and throws upon overflow
@@ -373,7 +989,7 @@ impl<'a> UncompiledFunction<'a> {
     /// You can also just use `v1 - v2` in your code instead of running this method,
     /// `&Val` has the `Sub` trait implemented so it can be subtracted with normal operators.
     pub fn insn_sub(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_sub)
+        self.insn_fold_binop(v1, v2, Constant::sub, jit_insn_sub)
     }
     #[inline(always)]
     /// Make an instruction that subtracts the second value from the first and throws upon overflow
@@ -386,7 +1002,7 @@ impl<'a> UncompiledFunction<'a> {
     /// You can also just use `v1 / v2` in your code instead of running this method,
     /// `&Val` has the `Div` trait implemented so it can be divided with normal operators.
     pub fn insn_div(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_div)
+        self.insn_fold_binop(v1, v2, Constant::div, jit_insn_div)
     }
     #[inline(always)]
     /// Make an instruction that finds the remainder when the first number is
@@ -395,7 +1011,7 @@ impl<'a> UncompiledFunction<'a> {
     /// You can also just use `v1 % v2` in your code instead of running this method,
     /// `&Val` has the `Rem` trait implemented so it can be done with normal operators.
     pub fn insn_rem(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_rem)
+        self.insn_fold_binop(v1, v2, Constant::rem, jit_insn_rem)
     }
     #[inline(always)]
     /// Make an instruction that checks if the first value is lower than or
@@ -410,6 +1026,30 @@ impl<'a> UncompiledFunction<'a> {
         self.insn_binop(v1, v2, jit_insn_ge)
     }
     #[inline(always)]
+    /// Make an instruction that finds the IEEE remainder when the first number
+    /// is divided by the second, which rounds the quotient to the nearest
+    /// integer instead of towards zero
+    pub fn insn_rem_ieee(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        expect!(insn_rem_ieee, v1, v2, primitive);
+        self.insn_binop(v1, v2, jit_insn_rem_ieee)
+    }
+    #[inline(always)]
+    /// Make an instruction that compares the values, giving -1 if the first is
+    /// lower, 0 if they're equal, or 1 if the first is greater. If either is
+    /// NaN this gives -1.
+    pub fn insn_cmpl(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        expect!(insn_cmpl, v1, v2, primitive);
+        self.insn_binop(v1, v2, jit_insn_cmpl)
+    }
+    #[inline(always)]
+    /// Make an instruction that compares the values, giving -1 if the first is
+    /// lower, 0 if they're equal, or 1 if the first is greater. If either is
+    /// NaN this gives 1.
+    pub fn insn_cmpg(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        expect!(insn_cmpg, v1, v2, primitive);
+        self.insn_binop(v1, v2, jit_insn_cmpg)
+    }
+    #[inline(always)]
     /// Make an instruction that checks if the first value is lower than the second
     pub fn insn_lt(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
         self.insn_binop(v1, v2, jit_insn_lt)
@@ -435,7 +1075,7 @@ impl<'a> UncompiledFunction<'a> {
     /// You can also just use `v1 & v2` in your code instead of running this method,
     /// `&Val` has the `BitAnd` trait implemented so it can be done with normal operators.
     pub fn insn_and(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_and)
+        self.insn_fold_binop(v1, v2, Constant::bitand, jit_insn_and)
     }
     #[inline(always)]
     /// Make an instruction that performs a bitwise or on the two values
@@ -443,7 +1083,7 @@ impl<'a> UncompiledFunction<'a> {
     /// You can also just use `v1 | v2` in your code instead of running this method,
     /// `&Val` has the `BitOr` trait implemented so it can be done with normal operators.
     pub fn insn_or(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_or)
+        self.insn_fold_binop(v1, v2, Constant::bitor, jit_insn_or)
     }
     #[inline(always)]
     /// Make an instruction that performs a bitwise xor on the two values
@@ -451,7 +1091,7 @@ impl<'a> UncompiledFunction<'a> {
     /// You can also just use `v1 ^ v2` in your code instead of running this method,
     /// `&Val` has the `BitXor` trait implemented so it can be done with normal operators.
     pub fn insn_xor(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_xor)
+        self.insn_fold_binop(v1, v2, Constant::bitxor, jit_insn_xor)
     }
     #[inline(always)]
     /// Make an instruction that performs a bitwise not on the two values
@@ -468,7 +1108,7 @@ impl<'a> UncompiledFunction<'a> {
     /// You can also just use `v1 << v2` in your code instead of running this method,
     /// `&Val` has the `Shl` trait implemented so it can be shifted with normal operators.
     pub fn insn_shl(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_shl)
+        self.insn_fold_binop(v1, v2, Constant::shl, jit_insn_shl)
     }
     #[inline(always)]
     /// Make an instruction that performs a right bitwise shift on the first
@@ -477,7 +1117,7 @@ impl<'a> UncompiledFunction<'a> {
     /// You can also just use `v1 >> v2` in your code instead of running this method,
     /// `&Val` has the `Shr` trait implemented so it can be shifted with normal operators.
     pub fn insn_shr(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
-        self.insn_binop(v1, v2, jit_insn_shr)
+        self.insn_fold_binop(v1, v2, Constant::shr, jit_insn_shr)
     }
     /// Make an instruction that performs a right bitwise shift on the first
     /// value by the second value
@@ -485,11 +1125,23 @@ impl<'a> UncompiledFunction<'a> {
         self.insn_binop(v1, v2, jit_insn_ushr)
     }
     #[inline(always)]
+    /// Make an instruction that performs a right bitwise shift on the first
+    /// value by the second value, always shifting in the sign bit even if the
+    /// first value is unsigned
+    pub fn insn_sshr(&self, v1: &'a Val, v2: &'a Val) -> &'a Val {
+        expect!(insn_sshr, v1, int);
+        expect!(insn_sshr, v2, int);
+        self.insn_binop(v1, v2, jit_insn_sshr)
+    }
+    #[inline(always)]
     /// Make an instruction that performs a negation on the value
     ///
     /// You can also just use `-value` in your code instead of running this method.
     /// `&Val` has the `Neg` trait implemented so it can be negatedd with normal operators.
     pub fn insn_neg(&self, value: &'a Val) -> &'a Val {
+        if let Some(constant) = self.fold_operand(value) {
+            return Val::new_constant(self, value.get_type().promote_int(), constant.neg())
+        }
         self.insn_unop(value, jit_insn_neg)
     }
     #[inline(always)]
@@ -524,6 +1176,98 @@ impl<'a> UncompiledFunction<'a> {
         }
     }
     #[inline(always)]
+    /// Make an instruction that loads the element of type `elem_type` at `index` from the
+    /// array pointed to by `base_addr`
+    pub fn insn_load_elem(&self, base_addr: &'a Val, index: &'a Val, elem_type: &Ty) -> &'a Val {
+        if cfg!(not(ndebug)) && !base_addr.get_type().is_pointer() {
+            panic!("Value given to insn_load_elem should be pointer, got {:?}", base_addr.get_type());
+        }
+        expect!(insn_load_elem, index, int);
+        unsafe {
+            from_ptr(jit_insn_load_elem(
+                self.into(),
+                base_addr.into(),
+                index.into(),
+                elem_type.into()
+            ))
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that stores `value` at `index` in the array pointed to by
+    /// `base_addr`
+    pub fn insn_store_elem(&self, base_addr: &'a Val, index: &'a Val, value: &'a Val) -> bool {
+        if cfg!(not(ndebug)) && !base_addr.get_type().is_pointer() {
+            panic!("Value given to insn_store_elem should be pointer, got {:?}", base_addr.get_type());
+        }
+        expect!(insn_store_elem, index, int);
+        unsafe {
+            jit_insn_store_elem(self.into(), base_addr.into(), index.into(), value.into()) != 0
+        }
+    }
+    /// Get the address of a struct value, or the value itself if it points to a
+    /// struct, along with the type of the struct
+    fn struct_addr(&self, value: &'a Val) -> (&'a Val, &'a Ty) {
+        let ty = value.get_type();
+        if ty.is_pointer() {
+            (value, ty.get_ref().unwrap())
+        } else {
+            (self.insn_address_of(value), ty)
+        }
+    }
+    /// Make an instruction that loads the field with the name given from `value`,
+    /// which can be a struct or a pointer to one
+    ///
+    /// You can also just use `value.field(func, name)` instead of running this method.
+    pub fn insn_load_field(&self, value: &'a Val, name: &str) -> &'a Val {
+        let (addr, ty) = self.struct_addr(value);
+        match ty.get_field(name) {
+            Some(field) => self.insn_load_relative(addr, field.get_offset(), field.get_type()),
+            None => panic!("No field {} on {:?}", name, ty)
+        }
+    }
+    /// Make an instruction that stores `value` in the field with the name given
+    /// of `dest`, which can be a struct or a pointer to one
+    pub fn insn_store_field(&self, dest: &'a Val, name: &str, value: &'a Val) {
+        let (addr, ty) = self.struct_addr(dest);
+        let field = match ty.get_field(name) {
+            Some(field) => field,
+            None => panic!("No field {} on {:?}", name, ty)
+        };
+        if cfg!(not(ndebug)) {
+            let field_t = field.get_type();
+            let value_t = value.get_type();
+            if !field_t.remove_tags().structurally_eq(value_t.remove_tags()) {
+                panic!("Bad value for field {} of {:?} - expected {:?}, got {:?}", name, ty, field_t, value_t);
+            }
+        }
+        self.insn_store_relative(addr, field.get_offset(), value);
+    }
+    /// Make instructions that load the element at `index` from `slice`, throwing an
+    /// out-of-bounds exception if `index` isn't less than the length of the slice
+    ///
+    /// The slice should have the same layout as a Rust slice, which is what
+    /// `get::<&[T]>()` gives you.
+    pub fn insn_index_checked(&self, slice: &'a Val, index: &'a Val) -> &'a Val {
+        expect!(insn_index_checked, index, int);
+        let slice_t = slice.get_type();
+        let mut fields = slice_t.fields();
+        let (data_f, len_f) = match (fields.next(), fields.next()) {
+            (Some(data), Some(len)) if data.get_type().is_pointer() && len.get_type().is_int() =>
+                (data, len),
+            _ => panic!("Value given to insn_index_checked should be slice, got {:?}", slice_t)
+        };
+        let elem_t = data_f.get_type().get_ref().unwrap();
+        let addr = self.insn_address_of(slice);
+        let data = self.insn_load_relative(addr, data_f.get_offset(), data_f.get_type());
+        let len = self.insn_load_relative(addr, len_f.get_offset(), len_f.get_type());
+        let index = self.insn_convert(index, consts::get_nuint(), false);
+        let mut in_bounds = Label::new(self);
+        self.insn_branch_if(self.insn_lt(index, len), &mut in_bounds);
+        self.insn_throw_builtin(BuiltinException::OutOfBounds);
+        self.insn_label(&mut in_bounds);
+        self.insn_load_elem(data, index, elem_t)
+    }
+    #[inline(always)]
     /// Make an instruction that stores the contents of `val` into `dest`, where `dest` is a
     /// temporary value or local value
     pub fn insn_store(&self, dest: &'a Val, val: &'a Val) {
@@ -542,6 +1286,41 @@ impl<'a> UncompiledFunction<'a> {
             jit_insn_store_relative(self.into(), dest.into(), offset as jit_nint, value.into());
         }
     }
+    #[inline]
+    /// Copy `ptr` into a new volatile temporary so accesses through it are
+    /// never cached, without marking the caller's value
+    fn volatile_address(&self, ptr: &'a Val) -> &'a Val {
+        if cfg!(not(ndebug)) && !ptr.get_type().is_pointer() {
+            panic!("Address given to a volatile access should be pointer, got {:?}", ptr.get_type());
+        }
+        let addr = Val::new(self, ptr.get_type());
+        addr.set_volatile();
+        self.insn_store(addr, ptr);
+        addr
+    }
+    #[inline]
+    /// Make an instruction that loads a value of the type given from the
+    /// address `ptr`, going through a volatile copy of the address
+    ///
+    /// Neither `ptr` nor the value loaded are marked volatile. LibJIT never
+    /// merges or removes loads through a volatile value, so every call makes
+    /// exactly one load in the compiled code, but it emits no memory fences so
+    /// this gives no ordering guarantees across threads.
+    pub fn insn_load_volatile(&self, ptr: &'a Val, ty: &Ty) -> &'a Val {
+        let addr = self.volatile_address(ptr);
+        self.insn_load_relative(addr, 0, ty)
+    }
+    #[inline]
+    /// Make an instruction that stores `value` at the address `ptr`, going
+    /// through a volatile copy of the address
+    ///
+    /// Neither `ptr` nor `value` are marked volatile. Like `insn_load_volatile`,
+    /// every call makes exactly one store in the compiled code, without any
+    /// memory fences.
+    pub fn insn_store_volatile(&self, ptr: &'a Val, value: &'a Val) {
+        let addr = self.volatile_address(ptr);
+        self.insn_store_relative(addr, 0, value);
+    }
     #[inline(always)]
     /// Make an instruction that sets a label
     pub fn insn_label(&self, label: &mut Label<'a>) {
@@ -570,6 +1349,92 @@ impl<'a> UncompiledFunction<'a> {
             jit_insn_branch_if_not(self.into(), value.into(), &mut **label);
         }
     }
+    #[inline]
+    /// End the current block and start a new one, even if nothing branches to it
+    pub fn insn_new_block(&self) {
+        unsafe {
+            if jit_insn_new_block(self.into()) == 0 {
+                oom()
+            }
+        }
+    }
+    #[inline]
+    /// Move the blocks from the block starting at `from` up to the block
+    /// starting at `to` to the end of the function
+    pub fn insn_move_blocks_to_end(&self, from: &Label<'a>, to: &Label<'a>) {
+        unsafe {
+            if jit_insn_move_blocks_to_end(self.into(), **from, **to) == 0 {
+                oom()
+            }
+        }
+    }
+    #[inline]
+    /// Move the blocks from the block starting at `from` up to the block
+    /// starting at `to` to the start of the function
+    pub fn insn_move_blocks_to_start(&self, from: &Label<'a>, to: &Label<'a>) {
+        unsafe {
+            if jit_insn_move_blocks_to_start(self.into(), **from, **to) == 0 {
+                oom()
+            }
+        }
+    }
+    /// Make the instructions for a path that rarely runs, such as error
+    /// handling, which are moved to the end of the function when it is
+    /// compiled so they stay out of the way of the code around them
+    ///
+    /// The block branches to the instructions it makes and they branch back
+    /// after, so it runs in the same place as it would without this.
+    ///
+    /// The instructions are only moved by `compile` and `compile_with`, which
+    /// also make a default return at the end of the function first so the code
+    /// before them can't fall through into them. A function compiled any other
+    /// way, like on demand by LibJIT, keeps them where they were made.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let x = func.param(0).unwrap();
+    /// func.insn_if(func.insn_lt(x, func.insn_of(0i32)), || func.insn_cold(|| {
+    ///     func.insn_throw_builtin(BuiltinException::OutOfBounds);
+    /// }));
+    /// func.insn_return(x);
+    /// ```
+    pub fn insn_cold<B>(&self, block: B) where B:FnOnce() {
+        let mut start = Label::new(self);
+        let mut end = Label::new(self);
+        self.insn_branch(&mut start);
+        self.insn_label(&mut start);
+        block();
+        self.insn_branch(&mut end);
+        self.insn_label(&mut end);
+        unsafe {
+            let mut paths = jit_function_get_meta(self.into(), COLD_META) as *mut ColdPaths;
+            if paths.is_null() {
+                paths = mem::transmute(Box::new(ColdPaths::new()));
+                if jit_function_set_meta(self.into(), COLD_META, paths as *mut c_void, Some(::free_data::<ColdPaths>), 1) == 0 {
+                    oom()
+                }
+            }
+            (*paths).push((*start, *end));
+        }
+    }
+    /// Move the cold paths made with `insn_cold` to the end of the function,
+    /// which can only be done once nothing else will be added after them
+    fn move_cold_paths(&self) {
+        unsafe {
+            let paths = jit_function_get_meta(self.into(), COLD_META) as *mut ColdPaths;
+            if !paths.is_null() {
+                // stop the last block falling through into the cold paths
+                self.insn_default_return();
+                for &(start, end) in (*paths).iter() {
+                    if jit_insn_move_blocks_to_end(self.into(), start, end) == 0 {
+                        oom()
+                    }
+                }
+            }
+        }
+    }
     #[inline(always)]
     /// Make an instruction that branches to a label in the table
     pub fn insn_jump_table(&self, value: &'a Val, labels: &mut [Label<'a>]) {
@@ -731,6 +1596,91 @@ impl<'a> UncompiledFunction<'a> {
         expect!(insn_sign, v, primitive);
         self.insn_unop(v, jit_insn_sign)
     }
+    /// Make instructions that create a value of the enum type given, which holds
+    /// the variant `variant` with the discriminant and fields given
+    ///
+    /// The type should have been made with `Type::new_enum` or `Type::new_c_enum`.
+    pub fn insn_make_variant(&self, ty: &Ty, variant: usize, discriminant: &'a Val, fields: &[&'a Val]) -> &'a Val {
+        let (offset, discriminant_t) = match ty.get_discriminant() {
+            Some(discriminant) => discriminant,
+            None => panic!("Type given to insn_make_variant should be enum, got {:?}", ty)
+        };
+        let value = Val::new(self, ty);
+        let addr = self.insn_address_of(value);
+        self.insn_store_relative(addr, offset, self.insn_convert(discriminant, discriminant_t, false));
+        for (index, field) in fields.iter().enumerate() {
+            match ty.get_variant_field(variant, index) {
+                Some((offset, _)) => self.insn_store_relative(addr, offset, field),
+                None => panic!("Variant {} of {:?} has no field {}", variant, ty, index)
+            }
+        }
+        value
+    }
+    /// Make an instruction that loads the discriminant of the enum value given
+    pub fn insn_discriminant(&self, value: &'a Val) -> &'a Val {
+        let ty = value.get_type();
+        match ty.get_discriminant() {
+            Some((offset, discriminant_t)) =>
+                self.insn_load_relative(self.insn_address_of(value), offset, discriminant_t),
+            None => panic!("Value given to insn_discriminant should be enum, got {:?}", ty)
+        }
+    }
+    /// Make an instruction that loads the field `field` of the variant `variant`
+    /// from the enum value given, without checking it actually holds that variant
+    pub fn insn_variant_field(&self, value: &'a Val, variant: usize, field: usize) -> &'a Val {
+        let ty = value.get_type();
+        match ty.get_variant_field(variant, field) {
+            Some((offset, field_t)) =>
+                self.insn_load_relative(self.insn_address_of(value), offset, field_t),
+            None => panic!("Variant {} of {:?} has no field {}", variant, ty, field)
+        }
+    }
+    #[inline(always)]
+    /// Make instructions that wrap the value in `Some`
+    pub fn insn_make_some(&self, value: &'a Val) -> &'a Val {
+        let ty = Type::new_option(value.get_type());
+        self.insn_make_variant(&ty, 1, self.insn_of(1u8), &[value])
+    }
+    #[inline(always)]
+    /// Make instructions that create a `None` of the type given
+    pub fn insn_make_none(&self, ty: &Ty) -> &'a Val {
+        let ty = Type::new_option(ty);
+        self.insn_make_variant(&ty, 0, self.insn_of(0u8), &[])
+    }
+    #[inline(always)]
+    /// Make an instruction that checks if the `Option` value given is `Some`
+    pub fn insn_is_some(&self, option: &'a Val) -> &'a Val {
+        self.insn_neq(self.insn_discriminant(option), self.insn_of(0u8))
+    }
+    /// Make instructions that get the contents of the `Option` value given if it
+    /// is `Some`, or `default` if it is `None`
+    pub fn insn_unwrap_or(&self, option: &'a Val, default: &'a Val) -> &'a Val {
+        let result = Val::new(self, default.get_type());
+        self.insn_store(result, default);
+        self.insn_if(self.insn_is_some(option), || {
+            self.insn_store(result, self.insn_variant_field(option, 1, 0));
+        });
+        result
+    }
+    #[inline(always)]
+    /// Make instructions that wrap the value in `Ok`, where `err` is the type
+    /// of the error
+    pub fn insn_make_ok(&self, value: &'a Val, err: &Ty) -> &'a Val {
+        let ty = Type::new_result(value.get_type(), err);
+        self.insn_make_variant(&ty, 0, self.insn_of(0u8), &[value])
+    }
+    #[inline(always)]
+    /// Make instructions that wrap the error in `Err`, where `ok` is the type
+    /// of the successful value
+    pub fn insn_make_err(&self, ok: &Ty, error: &'a Val) -> &'a Val {
+        let ty = Type::new_result(ok, error.get_type());
+        self.insn_make_variant(&ty, 1, self.insn_of(1u8), &[error])
+    }
+    #[inline(always)]
+    /// Make an instruction that checks if the `Result` value given is `Ok`
+    pub fn insn_is_ok(&self, result: &'a Val) -> &'a Val {
+        self.insn_eq(self.insn_discriminant(result), self.insn_of(0u8))
+    }
 
     /// Call the function, which may or may not be translated yet
     pub fn insn_call(&self, name:Option<&str>, func:&Func, sig:Option<&Ty>,
@@ -749,6 +1699,69 @@ impl<'a> UncompiledFunction<'a> {
         }
     }
     #[inline(always)]
+    /// Make instructions that control how calls are made by hand
+    ///
+    /// This is unsafe because the instructions bypass LibJIT's handling of
+    /// registers and the stack.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let x = func.param(0).unwrap();
+    /// unsafe {
+    ///     func.raw_call().push(x).pop_stack(1);
+    /// }
+    /// func.insn_return(x);
+    /// ```
+    pub unsafe fn raw_call<'b>(&'b self) -> RawCallBuilder<'a, 'b> {
+        RawCallBuilder {
+            func: self
+        }
+    }
+    /// Make an instruction that calls the function given in place of this one
+    /// and returns its result, so the caller's frame is reused and deep
+    /// recursion doesn't overflow the stack
+    ///
+    /// Since the call is always in return position, nothing made after it in
+    /// the same block will run. This fails without making any instructions
+    /// if the function called has a different signature to this one, either
+    /// function is nested, or an argument is addressable, which values whose
+    /// address was taken with `insn_address_of` are.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let x = func.param(0).unwrap();
+    /// assert_eq!(func.insn_tail_call(&func, &mut []), Err(TailCallError::ArgCount(1, 0)));
+    /// func.insn_if(x, || func.insn_tail_call(&func, &mut [x - 1i32]).unwrap());
+    /// func.insn_return(x);
+    /// ```
+    pub fn insn_tail_call(&self, func: &Func, args: &mut [&'a Val]) -> Result<(), TailCallError> {
+        if self.get_nested_parent().is_some() || func.get_nested_parent().is_some() {
+            return Err(TailCallError::Nested)
+        }
+        let signature = func.get_signature();
+        if !signature.structurally_eq(self.get_signature()) {
+            return Err(TailCallError::Signature)
+        }
+        let num_params = signature.params().count();
+        if num_params != args.len() {
+            return Err(TailCallError::ArgCount(num_params, args.len()))
+        }
+        if let Some(index) = args.iter().position(|arg| arg.is_addressable()) {
+            return Err(TailCallError::Addressable(index))
+        }
+        let value = self.insn_call(None, func, None, args, flags::CallFlags::TAIL);
+        if signature.get_return().map(|ret| ret.get_kind() == TypeKind::Void).unwrap_or(true) {
+            self.insn_default_return();
+        } else {
+            self.insn_return(value);
+        }
+        Ok(())
+    }
+    #[inline(always)]
     /// Make an instruction that calls a function that has the signature given
     /// with some arguments through a pointer to the fucntion
     pub fn insn_call_indirect(&self, func:&'a Val, signature: &Ty,
@@ -765,26 +1778,48 @@ impl<'a> UncompiledFunction<'a> {
             ))
         }
     }
+    /// Make an instruction that calls the method in the slot given from the
+    /// vtable `obj` points to, passing `obj` as the first argument
+    ///
+    /// The slot is a field of the vtable's type, as given by `VTable::get_type`,
+    /// and the vtable pointer should be the first field of the object. Nothing
+    /// checks the table is still alive when this runs, so the `VTable` must
+    /// outlive every call made through objects that point to it.
+    pub fn insn_call_vtable(&self, obj: &'a Val, slot: Field,
+                            args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
+        expect!(insn_call_vtable, obj, pointer);
+        let slot_t = slot.get_type();
+        let signature = match slot_t.get_ref() {
+            Some(signature) if signature.is_signature() => signature,
+            _ => panic!("Slot given to insn_call_vtable should point to a signature, got {:?}", slot_t)
+        };
+        let vtable = self.insn_load_relative(obj, 0, consts::get_void_ptr());
+        let method = self.insn_load_relative(vtable, slot.get_offset(), slot_t);
+        let mut all_args = Vec::with_capacity(args.len() + 1);
+        all_args.push(obj);
+        all_args.extend(args.iter().cloned());
+        if cfg!(not(ndebug)) {
+            check_args("vtable method", signature, &all_args);
+        }
+        unsafe {
+            let mut native_args: Vec<jit_value_t> = mem::transmute(all_args);
+            from_ptr(jit_insn_call_indirect_vtable(
+                self.into(),
+                method.into(),
+                signature.into(),
+                native_args.as_mut_ptr(),
+                native_args.len() as c_uint,
+                flags.bits()
+            ))
+        }
+    }
     /// Make an instruction that calls a native function that has the signature
     /// given with some arguments
-    fn insn_call_native(&self, name: Option<&str>,
-                        native_func: *mut c_void, signature: &Ty,
-                        args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
+    fn insn_call_native_raw(&self, name: Option<&str>,
+                            native_func: *mut c_void, signature: &Ty,
+                            args: &mut [&'a Val], flags: flags::CallFlags) -> &'a Val {
         if cfg!(not(ndebug)) {
-            let name = name.unwrap_or("unnamed function");
-            if !signature.is_signature() {
-                panic!("Bad signature for {} - expected signature, got {:?}", name, signature)
-            }
-            let num_sig_args = signature.params().count();
-            if args.len() != num_sig_args {
-                panic!("Bad arguments to {} - expected {}, got {}", name, num_sig_args, args.len());
-            }
-            for (index, (arg, param)) in args.iter().zip(signature.params()).enumerate() {
-                let ty = arg.get_type();
-                if ty != param {
-                    panic!("Bad argument #{} to {} - expected {:?}, got {:?}", index, name, param, ty);
-                }
-            }
+            check_args(name.unwrap_or("unnamed function"), signature, args);
         }
         unsafe {
             let mut native_args:&mut [jit_value_t] = mem::transmute(args);
@@ -801,6 +1836,56 @@ impl<'a> UncompiledFunction<'a> {
         }
     }
     #[inline(always)]
+    /// Make an instruction that calls a Rust function with the arguments given,
+    /// deriving the signature from the function's type
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// extern fn sum(a: i32, b: i32, c: i32, d: i32, e: i32) -> i32 {
+    ///     a + b + c + d + e
+    /// }
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    /// let args = [func.insn_of(1i32), func.insn_of(2i32), func.insn_of(3i32),
+    ///             func.insn_of(4i32), func.insn_of(5i32)];
+    /// let f: extern fn(i32, i32, i32, i32, i32) -> i32 = sum;
+    /// func.insn_return(func.insn_call_native(Some("sum"), f, args, flags::CallFlags::NO_THROW));
+    /// ```
+    pub fn insn_call_native<F>(&self, name: Option<&str>, native_func: F,
+                               mut args: F::Args, flags: flags::CallFlags) -> &'a Val
+                               where F: NativeFn<'a> {
+        let signature = get::<F>();
+        self.insn_call_native_raw(name, native_func.as_ptr(), &signature, F::args_slice(&mut args), flags)
+    }
+    /// Make an instruction that calls the Rust function registered on the
+    /// function's context under `name` with the arguments given
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// extern fn triple(x: i32) -> i32 {
+    ///     x * 3
+    /// }
+    /// let mut ctx = Context::<()>::new();
+    /// ctx.register("triple", triple as extern fn(i32) -> i32);
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    /// let flags = flags::CallFlags::NO_THROW;
+    /// let value = func.insn_call_symbol("triple", &mut [func.insn_of(14i32)], flags).unwrap();
+    /// func.insn_return(value);
+    /// assert_eq!(func.insn_call_symbol("quadruple", &mut [], flags).err(),
+    ///            Some(SymbolError::NotFound("quadruple".to_owned())));
+    /// ```
+    pub fn insn_call_symbol(&self, name: &str, args: &mut [&'a Val],
+                            flags: flags::CallFlags) -> Result<&'a Val, SymbolError> {
+        let symbols = unsafe { symbol::table(jit_function_get_context(self.into())) };
+        let symbol = try!(symbols.ok_or_else(|| SymbolError::NotFound(name.to_owned()))
+                                 .and_then(|symbols| symbols.get(name)));
+        let num_params = symbol.get_signature().params().count();
+        if num_params != args.len() {
+            return Err(SymbolError::ArgCount(num_params, args.len()))
+        }
+        Ok(self.insn_call_native_raw(Some(name), symbol.as_ptr(), symbol.get_signature(), args, flags))
+    }
+    #[inline(always)]
     /// Make an instruction that calls a Rust function that has the signature
     /// given with no arguments and expects a return value
     pub fn insn_call_native0<R>(&self, name: Option<&str>,
@@ -808,7 +1893,7 @@ impl<'a> UncompiledFunction<'a> {
                             signature: &Ty,
                             flags: flags::CallFlags) -> &'a Val {
         let func_ptr = unsafe { mem::transmute(native_func) };
-        self.insn_call_native(name, func_ptr, signature, &mut [], flags)
+        self.insn_call_native_raw(name, func_ptr, signature, &mut [], flags)
     }
     #[inline(always)]
     /// Make an instruction that calls a Rust function that has the signature
@@ -819,7 +1904,7 @@ impl<'a> UncompiledFunction<'a> {
                                 mut args: [&'a Val; 1],
                                 flags: flags::CallFlags) -> &'a Val {
         let func_ptr = unsafe { mem::transmute(native_func) };
-        self.insn_call_native(name, func_ptr, signature, &mut args, flags)
+        self.insn_call_native_raw(name, func_ptr, signature, &mut args, flags)
     }
     #[inline(always)]
     /// Make an instruction that calls a Rust function that has the signature
@@ -830,7 +1915,7 @@ impl<'a> UncompiledFunction<'a> {
                                 mut args: [&'a Val; 2],
                                 flags: flags::CallFlags) -> &'a Val {
         let func_ptr = unsafe { mem::transmute(native_func) };
-        self.insn_call_native(name, func_ptr, signature, &mut args, flags)
+        self.insn_call_native_raw(name, func_ptr, signature, &mut args, flags)
     }
     #[inline(always)]
     /// Make an instruction that calls a Rust function that has the signature
@@ -841,7 +1926,7 @@ impl<'a> UncompiledFunction<'a> {
                                 mut args: [&'a Val; 3],
                                 flags: flags::CallFlags) -> &'a Val {
         let func_ptr = unsafe { mem::transmute(native_func) };
-        self.insn_call_native(name, func_ptr, signature, &mut args, flags)
+        self.insn_call_native_raw(name, func_ptr, signature, &mut args, flags)
     }
     #[inline(always)]
     /// Make an instruction that calls a Rust function that has the signature
@@ -852,7 +1937,7 @@ impl<'a> UncompiledFunction<'a> {
                                 mut args: [&'a Val; 4],
                                 flags: flags::CallFlags) -> &'a Val {
         let func_ptr = unsafe { mem::transmute(native_func) };
-        self.insn_call_native(name, func_ptr, signature, &mut args
+        self.insn_call_native_raw(name, func_ptr, signature, &mut args
             , flags)
     }
     #[inline(always)]
@@ -888,6 +1973,30 @@ impl<'a> UncompiledFunction<'a> {
         }
     }
     #[inline(always)]
+    /// Make an instruction that adds the offset given to the pointer given,
+    /// like `insn_load_relative` does without loading
+    pub fn insn_add_relative(&self, value: &'a Val, offset: usize) -> &'a Val {
+        expect!(insn_add_relative, value, pointer);
+        unsafe {
+            from_ptr(jit_insn_add_relative(self.into(), value.into(), offset as jit_nint))
+        }
+    }
+    #[inline(always)]
+    /// Make an instruction that loads a small struct or union into a register,
+    /// or gives it back unchanged if it's too big for one
+    pub fn insn_load_small(&self, value: &'a Val) -> &'a Val {
+        expect!(insn_load_small, value, structure);
+        self.insn_unop(value, jit_insn_load_small)
+    }
+    #[inline(always)]
+    /// Make an instruction that gets the address of the label given, so it can
+    /// be jumped to indirectly
+    pub fn insn_address_of_label(&self, label: &mut Label<'a>) -> &'a Val {
+        unsafe {
+            from_ptr(jit_insn_address_of_label(self.into(), &mut **label))
+        }
+    }
+    #[inline(always)]
     /// Make an instruction that gets the address of a value
     pub fn insn_address_of(&self, value: &'a Val) -> &'a Val {
         unsafe {
@@ -906,6 +2015,33 @@ impl<'a> UncompiledFunction<'a> {
             from_ptr(f(self.into(), v1.into(), v2.into()))
         }
     }
+    #[inline]
+    /// Get the constant held by the value given if it can be folded
+    fn fold_operand(&self, value: &'a Val) -> Option<Constant> {
+        if self.is_folding() && value.get_type().is_primitive() {
+            value.as_constant()
+        } else {
+            None
+        }
+    }
+    #[inline]
+    fn insn_fold_binop(&self,
+                    v1: &'a Val, v2: &'a Val,
+                    fold: fn(Constant, Constant) -> Option<Constant>,
+                    f: unsafe extern "C" fn(
+                        jit_function_t,
+                        jit_value_t,
+                        jit_value_t) -> jit_value_t)
+                    -> &'a Val {
+        if let (Some(c1), Some(c2)) = (self.fold_operand(v1), self.fold_operand(v2)) {
+            if v1.get_type() == v2.get_type() {
+                if let Some(result) = fold(c1, c2) {
+                    return Val::new_constant(self, v1.get_type().promote_int(), result)
+                }
+            }
+        }
+        self.insn_binop(v1, v2, f)
+    }
     #[inline(always)]
     fn insn_unop(&self,
                     value: &'a Val,
@@ -945,25 +2081,220 @@ impl<'a> UncompiledFunction<'a> {
         else_block();
         self.insn_label(&mut end)
     }
+    #[inline]
+    /// Make a new variable that holds values of the Rust type `T`, starting
+    /// with the value given
+    pub fn var<'b, T>(&'b self, init: T) -> Var<'a, 'b, T> where T:Compile<'a> {
+        Var::new(self, init)
+    }
     /// Make instructions to run the block forever
     pub fn insn_loop<B>(&self, block: B) where B:FnOnce() {
-        let mut start = Label::new(self);
-        self.insn_label(&mut start);
-        block();
-        self.insn_branch(&mut start);
+        self.insn_loop_with(|_| block())
+    }
+    /// Make instructions to run the block forever, or until it breaks out
+    /// of the loop with the `LoopCtx` it is given
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let x = func.param(0).unwrap();
+    /// func.insn_loop_with(|lp| {
+    ///     lp.break_if(func.insn_geq(x, func.insn_of(100i32)));
+    ///     func.insn_store(x, x * 2i32);
+    /// });
+    /// func.insn_return(x);
+    /// ```
+    pub fn insn_loop_with<B>(&self, block: B) where B:FnOnce(&LoopCtx<'a>) {
+        let mut lp = LoopCtx::new(self);
+        self.insn_label(&mut lp.next);
+        block(&lp);
+        self.insn_branch(&mut lp.next);
+        self.insn_label(&mut lp.end);
     }
     /// Make instructions to run the block and continue running it so long
     /// as the condition is met
     pub fn insn_while<C, B>(&self, cond: C, block: B)
         where C:FnOnce() -> &'a Val, B:FnOnce() {
-        let mut start = Label::new(self);
-        self.insn_label(&mut start);
-        let mut after = Label::new(self);
+        self.insn_while_with(cond, |_| block())
+    }
+    /// Make instructions to run the block and continue running it so long
+    /// as the condition is met, where the block can break out of the loop or
+    /// continue by checking the condition again with the `LoopCtx` it is given
+    pub fn insn_while_with<C, B>(&self, cond: C, block: B)
+        where C:FnOnce() -> &'a Val, B:FnOnce(&LoopCtx<'a>) {
+        let mut lp = LoopCtx::new(self);
+        self.insn_label(&mut lp.next);
         let cond_v = cond();
-        self.insn_branch_if_not(cond_v, &mut after);
-        block();
-        self.insn_branch(&mut start);
-        self.insn_label(&mut after);
+        self.insn_branch_if_not(cond_v, &mut lp.end);
+        block(&lp);
+        self.insn_branch(&mut lp.next);
+        self.insn_label(&mut lp.end);
+    }
+    /// Make instructions to run the block with each value from `start` up to
+    /// `end` (exclusive) by `step`, or down to `end` if `step` is negative,
+    /// where continuing moves on to the next value
+    ///
+    /// When `step` is a constant or unsigned the direction is chosen while
+    /// building. Otherwise its sign is tested once before the loop starts.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    /// let sum = Val::new(&func, &get::<i32>());
+    /// func.insn_store(sum, func.insn_of(0i32));
+    /// func.insn_for_range(func.insn_of(0i32), func.insn_of(10i32), func.insn_of(1i32), |i, _| {
+    ///     func.insn_store(sum, sum + i);
+    /// });
+    /// func.insn_return(sum);
+    /// ```
+    pub fn insn_for_range<B>(&self, start: &'a Val, end: &'a Val, step: &'a Val, block: B)
+        where B:FnOnce(&'a Val, &LoopCtx<'a>) {
+        let step_t = step.get_type();
+        let descending = step.as_constant().map(|step| step.is_negative()).unwrap_or(false);
+        let negative = if step.is_constant() || is_unsigned(step_t) {
+            None
+        } else {
+            Some(self.insn_lt(step, Val::new_constant(self, step_t, Constant::Int(0))))
+        };
+        let index = Val::new(self, start.get_type());
+        self.insn_store(index, start);
+        let mut lp = LoopCtx::new(self);
+        let mut check = Label::new(self);
+        self.insn_label(&mut check);
+        let in_range = match negative {
+            Some(negative) => self.insn_select(negative, || self.insn_gt(index, end), || self.insn_lt(index, end)),
+            None if descending => self.insn_gt(index, end),
+            None => self.insn_lt(index, end)
+        };
+        self.insn_branch_if_not(in_range, &mut lp.end);
+        block(index, &lp);
+        self.insn_label(&mut lp.next);
+        self.insn_store(index, self.insn_add(index, step));
+        self.insn_branch(&mut check);
+        self.insn_label(&mut lp.end);
+    }
+    /// Make instructions that evaluate to the value made by `then` if the
+    /// condition is met, or the value made by `otherwise` converted to the
+    /// same type if it isn't
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let x = func.param(0).unwrap();
+    /// let zero = func.insn_of(0i32);
+    /// func.insn_return(func.insn_select(func.insn_lt(x, zero), || func.insn_neg(x), || x));
+    /// ```
+    pub fn insn_select<A, B>(&self, cond: &'a Val, then: A, otherwise: B) -> &'a Val
+        where A:FnOnce() -> &'a Val, B:FnOnce() -> &'a Val {
+        let mut other = Label::new(self);
+        let mut end = Label::new(self);
+        self.insn_branch_if_not(cond, &mut other);
+        let then_v = then();
+        let result_t = then_v.get_type();
+        let result = Val::new(self, result_t);
+        self.insn_store(result, then_v);
+        self.insn_branch(&mut end);
+        self.insn_label(&mut other);
+        let other_v = otherwise();
+        self.insn_store(result, self.insn_convert(other_v, result_t, false));
+        self.insn_label(&mut end);
+        result
+    }
+    /// Make instructions to run the block for the case matching the value
+    /// given, or the default block if none match
+    ///
+    /// Unlike C, control doesn't fall through to the next case. Dense cases
+    /// are dispatched with a jump table, where an index outside the table
+    /// falls through to the default block, and sparse cases are dispatched
+    /// with a tree of comparisons.
+    ///
+    /// When the value is unsigned, each case is read as the bits of a `u64`,
+    /// so `-1` matches `u64::MAX`. This panics if a case doesn't fit in the
+    /// value's type.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    /// let x = func.param(0).unwrap();
+    /// func.insn_switch(x, &[
+    ///     (1, &|| func.insn_return(func.insn_of(10i32))),
+    ///     (100, &|| func.insn_return(func.insn_of(20i32)))
+    /// ], || func.insn_return(x));
+    /// ```
+    pub fn insn_switch<D>(&self, value: &'a Val, cases: &[(i64, &Fn())], default: D) where D:FnOnce() {
+        expect!(insn_switch, value, int);
+        let value_t = value.get_type();
+        let unsigned = is_unsigned(value_t);
+        for &(case, _) in cases {
+            switch_case(value_t, case);
+        }
+        let mut sorted = cases.iter().enumerate().map(|(index, &(case, _))| (case, index)).collect::<Vec<_>>();
+        if unsigned {
+            sorted.sort_by(|a, b| (a.0 as u64).cmp(&(b.0 as u64)));
+        } else {
+            sorted.sort();
+        }
+        for pair in sorted.windows(2) {
+            if pair[0].0 == pair[1].0 {
+                panic!("Case {} given to insn_switch more than once", pair[0].0)
+            }
+        }
+        let mut labels = cases.iter().map(|_| Label::new(self)).collect::<Vec<_>>();
+        let mut default_label = Label::new(self);
+        let mut end = Label::new(self);
+        let dense = sorted.first().and_then(|&(min, _)| sorted.last().map(|&(max, _)| {
+            let range = max.wrapping_sub(min) as u64;
+            sorted.len() >= 4 && range < 2 * sorted.len() as u64
+        })).unwrap_or(false);
+        if dense {
+            let min = sorted[0].0;
+            let mut table = (0..(sorted[sorted.len() - 1].0.wrapping_sub(min) as u64 + 1))
+                .map(|_| default_label.clone())
+                .collect::<Vec<_>>();
+            for &(case, index) in &sorted {
+                table[case.wrapping_sub(min) as u64 as usize] = labels[index].clone();
+            }
+            let long_t = consts::get_long();
+            let offset = self.insn_sub(self.insn_convert(value, long_t, false),
+                                       Val::new_constant(self, long_t, Constant::Long(min)));
+            self.insn_jump_table(self.insn_convert(offset, consts::get_nuint(), false), &mut table);
+            self.insn_branch(&mut default_label);
+        } else {
+            self.insn_switch_tree(value, &sorted, &mut labels, &mut default_label);
+        }
+        for (&(_, block), label) in cases.iter().zip(labels.iter_mut()) {
+            self.insn_label(label);
+            block();
+            self.insn_branch(&mut end);
+        }
+        self.insn_label(&mut default_label);
+        default();
+        self.insn_label(&mut end);
+    }
+    /// Make a tree of comparisons that branches to the label of the case
+    /// matching the value, where the cases are sorted
+    fn insn_switch_tree(&self, value: &'a Val, cases: &[(i64, usize)],
+                        labels: &mut [Label<'a>], default: &mut Label<'a>) {
+        let value_t = value.get_type();
+        if cases.len() <= 3 {
+            for &(case, index) in cases {
+                let case = Val::new_constant(self, value_t, switch_case(value_t, case));
+                self.insn_branch_if(self.insn_eq(value, case), &mut labels[index]);
+            }
+            self.insn_branch(default);
+        } else {
+            let mid = cases.len() / 2;
+            let mut upper = Label::new(self);
+            let pivot = Val::new_constant(self, value_t, switch_case(value_t, cases[mid].0));
+            self.insn_branch_if_not(self.insn_lt(value, pivot), &mut upper);
+            self.insn_switch_tree(value, &cases[..mid], labels, default);
+            self.insn_label(&mut upper);
+            self.insn_switch_tree(value, &cases[mid..], labels, default);
+        }
     }
     #[inline(always)]
     /// Set the optimization level of the function, where the bigger the level,
@@ -980,6 +2311,40 @@ impl<'a> UncompiledFunction<'a> {
             jit_function_get_max_optimization_level()
         }
     }
+    /// Set whether arithmetic on constants is folded into a new constant
+    /// while building this function instead of making instructions
+    ///
+    /// This covers `add`, `sub`, `mul`, `div`, `rem`, `neg`, `and`, `or`, `xor`,
+    /// `shl` and `shr`. Integer division by zero or of the minimum value by -1
+    /// is never folded, so it still throws when the function runs.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i32>());
+    /// func.set_folding(true);
+    /// let six = func.insn_mul(func.insn_of(2i32), func.insn_of(3i32));
+    /// assert_eq!(six.as_constant(), Some(Constant::Int(6)));
+    /// ```
+    pub fn set_folding(&self, fold: bool) {
+        unsafe {
+            if fold {
+                // the flag just needs to be non-null so no data is allocated
+                if jit_function_set_meta(self.into(), FOLD_META, 1 as *mut c_void, None, 1) == 0 {
+                    ::alloc::oom()
+                }
+            } else {
+                jit_function_free_meta(self.into(), FOLD_META);
+            }
+        }
+    }
+    #[inline]
+    /// Check if arithmetic on constants is folded while building this function
+    pub fn is_folding(&self) -> bool {
+        unsafe {
+            !jit_function_get_meta(self.into(), FOLD_META).is_null()
+        }
+    }
     #[inline(always)]
     /// Make this function a candidate for recompilation
     pub fn set_recompilable(&self) {
@@ -1005,6 +2370,7 @@ impl<'a> UncompiledFunction<'a> {
         if !self.owned {
             panic!("The function must be owned")
         }
+        self.move_cold_paths();
         unsafe {
             let ptr = (&self).into();
             mem::forget(self);
@@ -59,14 +59,16 @@ use std::mem;
 pub use compile::Compile;
 pub use context::Context;
 pub use elf::*;
-pub use function::{flags, Abi, UncompiledFunction, Func, CompiledFunction};
+pub use function::{flags, Abi, BuiltinException, UncompiledFunction, Func, FuncParams, CompiledFunction, LoopCtx, NativeFn, RawCallBuilder, TailCallError};
 pub use function::flags::CallFlags;
 pub use label::Label;
+pub use symbol::{Symbol, SymbolTable, SymbolError};
 pub use insn::{Block, Instruction, InstructionIter};
 pub use types::kind::TypeKind;
-pub use types::{kind, get, Type, Field, Fields, Params, CowType, StaticType, Ty, TaggedType};
+pub use types::{kind, get, get_cached, cache_type, cache_shape, Type, Field, Fields, Params, CowType, StaticType, Ty, TaggedType, StructLayout, LayoutError};
 pub use types::consts as typecs;
-pub use value::Val;
+pub use value::{Val, Constant, Operand, StructBuilder, ValExt, Var};
+pub use vtable::{VTable, VTableBuilder};
 
 
 extern fn free_data<T>(data: *mut c_void) {
@@ -112,6 +114,8 @@ mod elf;
 mod function;
 mod insn;
 mod label;
+mod symbol;
 mod types;
 mod util;
 mod value;
+mod vtable;
@@ -6,7 +6,7 @@ use function::UncompiledFunction;
 use std::marker::PhantomData;
 use std::fmt;
 use std::ops::{Deref, DerefMut};
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 /// A label in the code that can be branched to in instructions
 pub struct Label<'a> {
     _label: jit_label_t,
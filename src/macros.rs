@@ -47,7 +47,11 @@ macro_rules! compile_func(
             }
             #[inline(always)]
             fn get_type() -> CowType<'a> {
-                Type::new_signature(CDecl, &get::<R>(), &mut [$(&get::<$arg>()),*]).into()
+                let parts = [&*get::<R>(), $(&*get::<$arg>()),*];
+                cache_shape(TypeKind::Signature, &parts, || {
+                    let mut parts = parts;
+                    Type::new_signature(CDecl, parts[0], &mut parts[1..])
+                })
             }
         }
         impl<'a, $($arg:Compile<'a>,)* R:Compile<'a>> Compile<'a> for $ext_sig {
@@ -57,7 +61,11 @@ macro_rules! compile_func(
             }
             #[inline(always)]
             fn get_type() -> CowType<'a> {
-                Type::new_signature(CDecl, &get::<R>(), &mut [$(&get::<$arg>()),*]).into()
+                let parts = [&*get::<R>(), $(&*get::<$arg>()),*];
+                cache_shape(TypeKind::Signature, &parts, || {
+                    let mut parts = parts;
+                    Type::new_signature(CDecl, parts[0], &mut parts[1..])
+                })
             }
         }
     )
@@ -78,12 +86,15 @@ macro_rules! compile_tuple(
             #[inline(always)]
             fn get_type() -> CowType<'a> {
                 use std::mem;
-                let mut types = [$(&*get::<$ty>()),+];
-                let ty = Type::new_struct(&mut types);
-                unsafe {
-                    jit_type_set_size_and_alignment((&ty).into(), mem::size_of::<Self>() as i64, mem::align_of::<Self>() as i64);
-                }
-                ty.into()
+                let types = [$(&*get::<$ty>()),+];
+                cache_shape(TypeKind::Struct, &types, || {
+                    let mut types = types;
+                    let ty = Type::new_struct(&mut types);
+                    unsafe {
+                        jit_type_set_size_and_alignment((&ty).into(), mem::size_of::<Self>() as i64, mem::align_of::<Self>() as i64);
+                    }
+                    ty
+                })
             }
         }
     )
@@ -0,0 +1,114 @@
+use raw::*;
+use function::NativeFn;
+use types::{get, Ty, Type};
+use libc::{c_int, c_void};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+/// The context meta key the symbol table is stored under
+pub const SYMBOL_META: c_int = 0x73796d73;
+/// A Rust function registered under a name, along with its signature
+pub struct Symbol {
+    func: *mut c_void,
+    signature: Type
+}
+impl Symbol {
+    #[inline(always)]
+    /// Get a pointer to the function's code
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.func
+    }
+    #[inline(always)]
+    /// Get the signature derived from the function's type
+    pub fn get_signature(&self) -> &Ty {
+        &self.signature
+    }
+}
+impl fmt::Debug for Symbol {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{:?} at {:?}", self.signature, self.func)
+    }
+}
+/// Maps names to the Rust functions that JIT code can call by name
+///
+/// Every `Context` has one, which functions are added to with `Context::register`.
+pub struct SymbolTable {
+    symbols: HashMap<String, Symbol>
+}
+impl SymbolTable {
+    #[inline]
+    /// Create an empty symbol table
+    pub fn new() -> SymbolTable {
+        SymbolTable {
+            symbols: HashMap::new()
+        }
+    }
+    /// Add the function given under `name`, deriving its signature from its
+    /// type and returning the symbol it replaced, if any
+    pub fn insert<F>(&mut self, name: &str, func: F) -> Option<Symbol> where F:NativeFn<'static> {
+        let symbol = Symbol {
+            func: func.as_ptr(),
+            signature: get::<F>().into_owned()
+        };
+        self.symbols.insert(name.to_owned(), symbol)
+    }
+    #[inline]
+    /// Look up the symbol with the name given
+    pub fn get(&self, name: &str) -> Result<&Symbol, SymbolError> {
+        self.symbols.get(name).ok_or_else(|| SymbolError::NotFound(name.to_owned()))
+    }
+    #[inline]
+    /// Check if there is a symbol with the name given
+    pub fn contains(&self, name: &str) -> bool {
+        self.symbols.contains_key(name)
+    }
+    #[inline]
+    /// Get the number of symbols in the table
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+    #[inline]
+    /// Check if the table has no symbols
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+/// Get the symbol table stored on the context given, if one has been made
+pub fn table<'a>(ctx: jit_context_t) -> Option<&'a SymbolTable> {
+    unsafe {
+        let meta = jit_context_get_meta(ctx, SYMBOL_META);
+        if meta.is_null() {
+            None
+        } else {
+            Some(mem::transmute(meta))
+        }
+    }
+}
+/// An error from calling a function by name
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SymbolError {
+    /// No function is registered under the name
+    NotFound(String),
+    /// The number of arguments given doesn't match the function's signature,
+    /// with the number of parameters first
+    ArgCount(usize, usize)
+}
+impl fmt::Display for SymbolError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SymbolError::NotFound(ref name) =>
+                write!(fmt, "{}: '{}'", self.description(), name),
+            SymbolError::ArgCount(params, args) =>
+                write!(fmt, "{}: expected {} but got {}", self.description(), params, args)
+        }
+    }
+}
+impl Error for SymbolError {
+    fn description(&self) -> &'static str {
+        match *self {
+            SymbolError::NotFound(_) => "No function is registered under that name",
+            SymbolError::ArgCount(_, _) => "Wrong number of arguments"
+        }
+    }
+}
@@ -2,14 +2,19 @@ use raw::*;
 use compile::Compile;
 use function::Abi;
 use alloc::oom;
-use libc::{c_char, c_uint, c_void};
+use libc::{c_char, c_int, c_uint, c_void};
 use util::{from_ptr, from_ptr_opt};
+use std::any::{Any, TypeId};
 use std::borrow::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::marker::PhantomData;
-use std::{fmt, mem, str};
+use std::{cmp, fmt, mem, ptr, str};
 use std::iter::IntoIterator;
 use std::ffi::{self, CString};
 use std::ops::{Deref, DerefMut};
+use std::error::Error;
+use std::hash::{Hash, Hasher, SipHasher};
 
 pub use kind::TypeKind;
 /// The integer representation of a type
@@ -36,89 +41,163 @@ pub mod kind {
             const Signature = 16,
             const Pointer = 17,
             const FirstTagged = 2,
+            const Name = 10000,
+            const StructName = 10001,
+            const UnionName = 10002,
+            const EnumName = 10003,
+            const Const = 10004,
+            const Volatile = 10005,
+            const Reference = 10006,
+            const Output = 10007,
+            const Restrict = 10008,
             const SysBool = 10009,
             const SysChar = 10010
         }
     );
 }
 impl fmt::Debug for Ty {
+    /// Write this type in a Rust-like syntax, with tags such as names and
+    /// qualifiers written around the type they are tagged to
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let f64_t = get::<f64>();
+    /// let mut pos_t = Type::new_struct(&mut [&f64_t, &f64_t]);
+    /// assert_eq!(format!("{:?}", pos_t), "(f64, f64)");
+    /// pos_t.set_names(&["x", "y"]);
+    /// assert_eq!(format!("{:?}", pos_t), "{x: f64, y: f64}");
+    /// assert_eq!(format!("{:?}", Type::new_pointer(&pos_t)), "&mut {x: f64, y: f64}");
+    /// let sig_t = Type::new_signature(Abi::FastCall, &f64_t, &mut [&pos_t]);
+    /// assert_eq!(format!("{:?}", sig_t), "extern \"fastcall\" fn({x: f64, y: f64}) -> f64");
+    /// ```
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let kind = self.get_kind();
-        if kind.contains(TypeKind::SysChar) {
-            fmt.write_str("char")
-        } else if kind.contains(TypeKind::SysBool) {
-            fmt.write_str("bool")
-        } else if kind.contains(TypeKind::Pointer) {
-            try!(fmt.write_str("&mut"));
-            write!(fmt, "&mut {:?}", self.get_ref().unwrap())
-        } else if kind.contains(TypeKind::Signature) {
-            try!(fmt.write_str("fn("));
-            let params = self.params();
-            let (size, _) = params.size_hint();
-            for (i, arg) in params.enumerate() {
-                try!(write!(fmt, "{:?}", arg));
-                if i < size - 1 {
-                    try!(fmt.write_str(", "));
-                }
-            }
-            try!(fmt.write_str(")"));
-            if let Some(x) = self.get_return() {
-                if !x.get_kind().contains(TypeKind::Void) {
-                    try!(write!(fmt, " -> {:?}", x))
-                }
-            }
-            Ok(())
-        } else if kind.contains(TypeKind::Struct) {
-            try!(fmt.write_str("("));
-            let fields = self.fields();
-            let (size, _) = fields.size_hint();
-            for (i, field) in fields.enumerate() {
-                try!(write!(fmt, "{:?}", field.get_type()));
-                if i < size - 1 {
-                    try!(fmt.write_str(", "));
-                }
+        fmt_type(self, fmt, &mut Vec::new())
+    }
+}
+impl fmt::Display for Ty {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, fmt)
+    }
+}
+/// Write the type given, stopping at types that contain themselves
+fn fmt_type(ty: &Ty, fmt: &mut fmt::Formatter, parents: &mut Vec<jit_type_t>) -> fmt::Result {
+    let ptr:jit_type_t = ty.into();
+    if parents.contains(&ptr) {
+        return fmt.write_str("..")
+    }
+    parents.push(ptr);
+    let result = fmt_type_inner(ty, fmt, parents);
+    parents.pop();
+    result
+}
+fn fmt_types<'a, I>(types: I, fmt: &mut fmt::Formatter, parents: &mut Vec<jit_type_t>) -> fmt::Result where I:Iterator<Item=&'a Ty> {
+    for (i, ty) in types.enumerate() {
+        if i > 0 {
+            try!(fmt.write_str(", "));
+        }
+        try!(fmt_type(ty, fmt, parents));
+    }
+    Ok(())
+}
+fn fmt_fields(ty: &Ty, fmt: &mut fmt::Formatter, parents: &mut Vec<jit_type_t>) -> fmt::Result {
+    if ty.fields().any(|field| field.get_name().is_some()) {
+        try!(fmt.write_str("{"));
+        for (i, field) in ty.fields().enumerate() {
+            if i > 0 {
+                try!(fmt.write_str(", "));
             }
-            fmt.write_str(")")
-        } else if kind.contains(TypeKind::Union) {
-            try!(fmt.write_str("union("));
-            let fields = self.fields();
-            let (size, _) = fields.size_hint();
-            for (i, field) in fields.enumerate() {
-                try!(write!(fmt, "{:?}", field.get_type()));
-                if i < size - 1 {
-                    try!(fmt.write_str(", "));
-                }
+            match field.get_name() {
+                Some(name) => try!(write!(fmt, "{}: ", name)),
+                None => try!(write!(fmt, "{}: ", i))
             }
-            fmt.write_str(")")
-        } else if kind.contains(TypeKind::NFloat) {
-            fmt.write_str("float")
-        } else if kind.contains(TypeKind::Float32) {
-            fmt.write_str("f32")
-        } else if kind.contains(TypeKind::Float64) {
-            fmt.write_str("f64")
-        } else if kind.contains(TypeKind::ULong) {
-            fmt.write_str("u64")
-        } else if kind.contains(TypeKind::Long) {
-            fmt.write_str("i64")
-        } else if kind.contains(TypeKind::NUInt) {
-            fmt.write_str("usize")
-        } else if kind.contains(TypeKind::NInt) {
-            fmt.write_str("isize")
-        } else if kind.contains(TypeKind::UInt) {
-            fmt.write_str("u32")
-        } else if kind.contains(TypeKind::Int) {
-            fmt.write_str("i32")
-        } else if kind.contains(TypeKind::UShort) {
-            fmt.write_str("u16")
-        } else if kind.contains(TypeKind::Short) {
-            fmt.write_str("i16")
-        } else if kind.contains(TypeKind::UByte) {
-            fmt.write_str("u8")
-        } else if kind.contains(TypeKind::SByte) {
-            fmt.write_str("i8")
+            try!(fmt_type(field.get_type(), fmt, parents));
+        }
+        fmt.write_str("}")
+    } else {
+        try!(fmt.write_str("("));
+        try!(fmt_types(ty.fields().map(|field| field.get_type()), fmt, parents));
+        fmt.write_str(")")
+    }
+}
+fn fmt_type_inner(ty: &Ty, fmt: &mut fmt::Formatter, parents: &mut Vec<jit_type_t>) -> fmt::Result {
+    if ty.is_tagged() {
+        let (kind, inner) = ty.get_tagged();
+        let data = unsafe { jit_type_get_tagged_data(ty.into()) };
+        let is = |tag: TypeKind| kind == tag.bits();
+        let name = || unsafe {
+            str::from_utf8(ffi::CStr::from_ptr(data as *const c_char).to_bytes()).unwrap_or("?")
+        };
+        if is(TypeKind::SysBool) {
+            return fmt.write_str("bool")
+        } else if is(TypeKind::SysChar) {
+            return fmt.write_str("char")
+        } else if data.is_null() && (is(TypeKind::Name) || is(TypeKind::StructName)
+                                     || is(TypeKind::UnionName) || is(TypeKind::EnumName)) {
+            // unnamed, so just write the underlying type
+        } else if is(TypeKind::Name) || is(TypeKind::EnumName) {
+            return fmt.write_str(name())
+        } else if is(TypeKind::StructName) {
+            try!(write!(fmt, "struct {} ", name()));
+        } else if is(TypeKind::UnionName) {
+            try!(write!(fmt, "union {} ", name()));
+        } else if is(TypeKind::Const) {
+            try!(fmt.write_str("const "));
+        } else if is(TypeKind::Volatile) {
+            try!(fmt.write_str("volatile "));
+        } else if is(TypeKind::Reference) {
+            try!(fmt.write_str("&"));
+        } else if is(TypeKind::Output) {
+            try!(fmt.write_str("out "));
+        } else if is(TypeKind::Restrict) {
+            try!(fmt.write_str("restrict "));
+        } else if kind == DISCRIMINANT_TAG {
+            // the discriminant of an enum, so just write its type
         } else {
-            fmt.write_str("()")
+            try!(write!(fmt, "tagged<{}>(", kind));
+            try!(fmt_type(inner, fmt, parents));
+            return fmt.write_str(")")
+        }
+        return fmt_type(inner, fmt, parents)
+    }
+    let kind = ty.get_kind();
+    if kind == TypeKind::Pointer {
+        try!(fmt.write_str("&mut "));
+        fmt_type(ty.get_ref().unwrap(), fmt, parents)
+    } else if kind == TypeKind::Signature {
+        try!(fmt.write_str(match ty.abi() {
+            Abi::CDecl | Abi::VarArg => "extern \"C\" fn(",
+            Abi::StdCall => "extern \"stdcall\" fn(",
+            Abi::FastCall => "extern \"fastcall\" fn("
+        }));
+        try!(fmt_types(ty.params(), fmt, parents));
+        if ty.abi() == Abi::VarArg {
+            try!(fmt.write_str(if ty.params().count() > 0 { ", ..." } else { "..." }));
+        }
+        try!(fmt.write_str(")"));
+        match ty.get_return() {
+            Some(ret) if ret.get_kind() != TypeKind::Void => {
+                try!(fmt.write_str(" -> "));
+                fmt_type(ret, fmt, parents)
+            },
+            _ => Ok(())
         }
+    } else if kind == TypeKind::Struct {
+        fmt_fields(ty, fmt, parents)
+    } else if kind == TypeKind::Union {
+        try!(fmt.write_str("union"));
+        fmt_fields(ty, fmt, parents)
+    } else {
+        let names = [
+            (TypeKind::SByte, "i8"), (TypeKind::UByte, "u8"),
+            (TypeKind::Short, "i16"), (TypeKind::UShort, "u16"),
+            (TypeKind::Int, "i32"), (TypeKind::UInt, "u32"),
+            (TypeKind::NInt, "isize"), (TypeKind::NUInt, "usize"),
+            (TypeKind::Long, "i64"), (TypeKind::ULong, "u64"),
+            (TypeKind::Float32, "f32"), (TypeKind::Float64, "f64"),
+            (TypeKind::NFloat, "nfloat")
+        ];
+        let name = names.iter().find(|&&(other, _)| other == kind).map(|&(_, name)| name);
+        fmt.write_str(name.unwrap_or("()"))
     }
 }
 impl fmt::Debug for Type {
@@ -126,6 +205,11 @@ impl fmt::Debug for Type {
         fmt::Debug::fmt(self.deref(), fmt)
     }
 }
+impl fmt::Display for Type {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.deref(), fmt)
+    }
+}
 /// Type constants
 pub mod consts {
     use util::from_ptr;
@@ -288,9 +372,50 @@ pub struct Ty(PhantomData<[()]>);
 native_ref!(&Ty = jit_type_t);
 impl ToOwned for Ty {
     type Owned = Type;
+    /// Make an owned type descriptor that changing won't change this one
+    ///
+    /// Structs, unions and signatures can have their names and layouts set, so
+    /// they are copied, while every other kind of type is shared by increasing
+    /// its reference count.
     fn to_owned(&self) -> Type {
         unsafe {
-            from_ptr(jit_type_copy(self.into()))
+            let ty = self.into();
+            if self.is_struct() || self.is_union() {
+                let mut fields = self.fields().map(|field| field.get_type().into()).collect::<Vec<jit_type_t>>();
+                let copy = if self.is_struct() {
+                    jit_type_create_struct(fields.as_mut_ptr(), fields.len() as c_uint, 1)
+                } else {
+                    jit_type_create_union(fields.as_mut_ptr(), fields.len() as c_uint, 1)
+                };
+                copy_names(ty, copy, fields.len());
+                if self.is_struct() {
+                    for (index, field) in self.fields().enumerate() {
+                        jit_type_set_offset(copy, index as c_uint, field.get_offset() as jit_nuint);
+                    }
+                }
+                jit_type_set_size_and_alignment(copy, self.get_size() as jit_nint, self.get_alignment() as jit_nint);
+                from_ptr(copy)
+            } else if self.is_signature() {
+                let mut params = self.params().map(|param| param.into()).collect::<Vec<jit_type_t>>();
+                let copy = jit_type_create_signature(jit_type_get_abi(ty), jit_type_get_return(ty),
+                                                     params.as_mut_ptr(), params.len() as c_uint, 1);
+                copy_names(ty, copy, params.len());
+                from_ptr(copy)
+            } else {
+                from_ptr(jit_type_copy(ty))
+            }
+        }
+    }
+}
+/// Give the copy of a struct, union or signature the names of the fields or
+/// parameters of the type it was copied from
+unsafe fn copy_names(ty: jit_type_t, copy: jit_type_t, count: usize) {
+    let mut names = (0..count)
+        .map(|index| jit_type_get_name(ty, index as c_uint) as *mut c_char)
+        .collect::<Vec<_>>();
+    if names.iter().any(|name| !name.is_null()) {
+        if jit_type_set_names(copy, names.as_mut_ptr(), count as c_uint) == 0 {
+            oom();
         }
     }
 }
@@ -395,6 +520,220 @@ impl Type {
             from_ptr(ptr)
         }
     }
+    /// Create a type descriptor for an enum whose variants carry data, laid out
+    /// like a `#[repr(u8)]` enum in Rust: a union of structs that each start with
+    /// the discriminant and are followed by the fields of that variant.
+    ///
+    /// Each variant is given as a struct of its fields.
+    pub fn new_enum(discriminant: &Ty, variants: &mut [&Ty]) -> Type {
+        let discriminant = tag_discriminant(discriminant);
+        let variants = variants.iter().map(|variant| {
+            let mut fields = vec![&*discriminant];
+            fields.extend(variant.fields().map(|field| field.get_type()));
+            Type::new_struct(&mut fields)
+        }).collect::<Vec<_>>();
+        let mut variants = variants.iter().map(|variant| &**variant).collect::<Vec<_>>();
+        Type::new_union(&mut variants)
+    }
+    /// Create a type descriptor for an enum whose variants carry data, laid out
+    /// like a `#[repr(C)]` enum in Rust: a struct of the discriminant followed by
+    /// a union of the variants.
+    ///
+    /// Each variant is given as a struct of its fields.
+    pub fn new_c_enum(discriminant: &Ty, variants: &mut [&Ty]) -> Type {
+        let union = Type::new_union(variants);
+        Type::new_struct(&mut [&tag_discriminant(discriminant), &union])
+    }
+    /// Create a type descriptor for an `Option` of the type given.
+    ///
+    /// Rust doesn't guarantee the layout of `Option`, but in practice an `Option`
+    /// of a type with no invalid values (like a number) has a discriminant as
+    /// wide as the alignment of the type it holds.
+    pub fn new_option(value: &Ty) -> Type {
+        let discriminant = discriminant_for(value.get_alignment());
+        Type::new_enum(discriminant, &mut [&Type::new_struct(&mut []), &Type::new_struct(&mut [value])])
+    }
+    /// Create a type descriptor for a `Result` of the types given.
+    ///
+    /// This has the same caveats as `Type::new_option`.
+    pub fn new_result(ok: &Ty, err: &Ty) -> Type {
+        let discriminant = discriminant_for(cmp::min(ok.get_alignment(), err.get_alignment()));
+        Type::new_enum(discriminant, &mut [&Type::new_struct(&mut [ok]), &Type::new_struct(&mut [err])])
+    }
+}
+/// The kind of the tag on the discriminant of enum types, so they can be told
+/// apart from unions and structs of the same shape
+///
+/// LibJIT's own tag kinds start at 10000, so this doesn't clash with them.
+const DISCRIMINANT_TAG: c_int = 7000;
+/// Tag the type given as the discriminant of an enum
+fn tag_discriminant(ty: &Ty) -> Type {
+    unsafe {
+        from_ptr(jit_type_create_tagged(ty.into(), DISCRIMINANT_TAG, ptr::null_mut(), None, 1))
+    }
+}
+/// Get the unsigned integer type Rust would use for the discriminant of an enum
+/// whose fields start at the alignment given
+fn discriminant_for(align: usize) -> StaticType {
+    match align {
+        0 | 1 => consts::get_ubyte(),
+        2 => consts::get_ushort(),
+        4 => consts::get_uint(),
+        _ => consts::get_ulong()
+    }
+}
+/// An error from checking the layout of a struct type against a Rust type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    /// The sizes differ, with the size of the struct type first
+    Size(usize, usize),
+    /// The alignments differ, with the alignment of the struct type first
+    Alignment(usize, usize)
+}
+impl fmt::Display for LayoutError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LayoutError::Size(ty, rust) =>
+                write!(fmt, "{}: struct is {} bytes but the Rust type is {}", self.description(), ty, rust),
+            LayoutError::Alignment(ty, rust) =>
+                write!(fmt, "{}: struct is aligned to {} bytes but the Rust type is aligned to {}", self.description(), ty, rust)
+        }
+    }
+}
+impl Error for LayoutError {
+    fn description(&self) -> &'static str {
+        match *self {
+            LayoutError::Size(_, _) => "Struct size doesn't match the Rust type",
+            LayoutError::Alignment(_, _) => "Struct alignment doesn't match the Rust type"
+        }
+    }
+}
+struct LayoutField<'a> {
+    name: &'a str,
+    ty: &'a Ty,
+    offset: Option<usize>
+}
+/// Builds a struct type with control over where each field is placed, as well
+/// as the size and alignment of the struct.
+///
+/// ```rust
+/// use jit::*;
+/// #[repr(C)]
+/// struct Header {
+///     tag: u8,
+///     len: u32
+/// }
+/// let u8_t = get::<u8>();
+/// let u32_t = get::<u32>();
+/// let header_t = StructLayout::new()
+///     .field("tag", &u8_t)
+///     .field("len", &u32_t)
+///     .build_for::<Header>()
+///     .unwrap();
+/// assert_eq!(header_t.get_field("len").unwrap().get_offset(), 4);
+/// ```
+pub struct StructLayout<'a> {
+    fields: Vec<LayoutField<'a>>,
+    packed: bool,
+    size: Option<usize>,
+    alignment: Option<usize>
+}
+impl<'a> StructLayout<'a> {
+    /// Make a new layout with no fields
+    pub fn new() -> StructLayout<'a> {
+        StructLayout {
+            fields: Vec::new(),
+            packed: false,
+            size: None,
+            alignment: None
+        }
+    }
+    /// Add a field with the name and type given, which is placed after the
+    /// last field
+    pub fn field(mut self, name: &'a str, ty: &'a Ty) -> StructLayout<'a> {
+        self.fields.push(LayoutField {
+            name: name,
+            ty: ty,
+            offset: None
+        });
+        self
+    }
+    /// Add a field with the name and type given at the offset given
+    pub fn field_at(mut self, name: &'a str, ty: &'a Ty, offset: usize) -> StructLayout<'a> {
+        self.fields.push(LayoutField {
+            name: name,
+            ty: ty,
+            offset: Some(offset)
+        });
+        self
+    }
+    /// Place the fields right after each other without any padding, like
+    /// `#[repr(packed)]` does
+    pub fn packed(mut self) -> StructLayout<'a> {
+        self.packed = true;
+        self
+    }
+    /// Set the size of the struct in bytes
+    pub fn size(mut self, size: usize) -> StructLayout<'a> {
+        self.size = Some(size);
+        self
+    }
+    /// Set the alignment of the struct in bytes
+    pub fn alignment(mut self, alignment: usize) -> StructLayout<'a> {
+        self.alignment = Some(alignment);
+        self
+    }
+    /// Make the struct type
+    pub fn build(&self) -> Type {
+        let mut types = self.fields.iter().map(|field| field.ty).collect::<Vec<_>>();
+        let names = self.fields.iter().map(|field| field.name).collect::<Vec<_>>();
+        let mut ty = Type::new_struct(&mut types);
+        if names.len() > 0 {
+            ty.set_names(&names);
+        }
+        let mut end = 0;
+        for (index, field) in self.fields.iter().enumerate() {
+            let offset = match field.offset {
+                Some(offset) => offset,
+                None if self.packed => end,
+                None => {
+                    let align = field.ty.get_alignment();
+                    (end + align - 1) / align * align
+                }
+            };
+            if field.offset.is_some() || self.packed {
+                unsafe {
+                    jit_type_set_offset((&ty).into(), index as c_uint, offset as jit_nuint);
+                }
+            }
+            end = cmp::max(end, offset + field.ty.get_size());
+        }
+        if self.packed || self.size.is_some() || self.alignment.is_some() {
+            let alignment = self.alignment.unwrap_or(if self.packed {
+                1
+            } else {
+                self.fields.iter().map(|field| field.ty.get_alignment()).max().unwrap_or(1)
+            });
+            let size = self.size.unwrap_or((end + alignment - 1) / alignment * alignment);
+            unsafe {
+                jit_type_set_size_and_alignment((&ty).into(), size as jit_nint, alignment as jit_nint);
+            }
+        }
+        ty
+    }
+    /// Make the struct type, checking it has the same size and alignment as the
+    /// Rust type `T`
+    pub fn build_for<T>(&self) -> Result<Type, LayoutError> {
+        let ty = self.build();
+        let (size, alignment) = (mem::size_of::<T>(), mem::align_of::<T>());
+        if ty.get_size() != size {
+            Err(LayoutError::Size(ty.get_size(), size))
+        } else if ty.get_alignment() != alignment {
+            Err(LayoutError::Alignment(ty.get_alignment(), alignment))
+        } else {
+            Ok(ty)
+        }
+    }
 }
 impl Ty {
     #[inline(always)]
@@ -502,6 +841,55 @@ impl Ty {
             }
         }
     }
+    /// Get the offset and type of the discriminant of an enum type made by
+    /// `Type::new_enum` or `Type::new_c_enum`, or none if this isn't one.
+    ///
+    /// Those constructors tag the discriminant, so a union or struct with the
+    /// same shape as an enum isn't mistaken for one.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let opt_t = get::<Option<i64>>();
+    /// let (offset, ty) = opt_t.get_discriminant().unwrap();
+    /// assert_eq!(offset, 0);
+    /// assert_eq!(ty, typecs::get_ulong());
+    /// let pair_t = Type::new_struct(&mut [typecs::get_ulong(), typecs::get_long()]);
+    /// assert!(Type::new_union(&mut [&pair_t, typecs::get_long()]).get_discriminant().is_none());
+    /// ```
+    pub fn get_discriminant(&self) -> Option<(usize, &Ty)> {
+        let field = if self.is_union() {
+            self.fields().next().and_then(|variant| variant.get_type().fields().next())
+        } else if self.is_struct() {
+            self.fields().next()
+        } else {
+            None
+        };
+        field.and_then(|field| {
+            let ty = field.get_type();
+            if ty.is_tagged() && ty.get_tagged().0 == DISCRIMINANT_TAG {
+                Some((field.get_offset(), ty.get_tagged().1))
+            } else {
+                None
+            }
+        })
+    }
+    /// Get the offset and type of the field `field` of the variant `variant` of
+    /// an enum type made by `Type::new_enum` or `Type::new_c_enum`, or none if
+    /// there isn't one.
+    pub fn get_variant_field(&self, variant: usize, field: usize) -> Option<(usize, &Ty)> {
+        if self.get_discriminant().is_none() {
+            None
+        } else if self.is_union() {
+            self.fields().nth(variant)
+                .and_then(|variant| variant.get_type().fields().nth(field + 1))
+                .map(|field| (field.get_offset(), field.get_type()))
+        } else {
+            let union = self.fields().nth(1).unwrap();
+            union.get_type().fields().nth(variant)
+                .and_then(|variant| variant.get_type().fields().nth(field)
+                    .map(|field| (union.get_offset() + variant.get_offset() + field.get_offset(), field.get_type())))
+        }
+    }
     #[inline(always)]
     /// Check if this is a primitive
     ///
@@ -587,6 +975,167 @@ impl Ty {
             jit_type_is_tagged(self.into()) != 0
         }
     }
+    #[inline(always)]
+    /// Check if this is tagged with the kind given, or has a tag of that kind
+    /// somewhere underneath its other tags
+    pub fn has_tag(&self, kind: TypeKind) -> bool {
+        unsafe {
+            jit_type_has_tag(self.into(), kind.bits()) != 0
+        }
+    }
+    #[inline(always)]
+    /// Get the application binary interface of this signature type
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let sig_t = Type::new_signature(Abi::FastCall, &get::<i32>(), &mut []);
+    /// assert_eq!(sig_t.abi(), Abi::FastCall);
+    /// ```
+    pub fn abi(&self) -> Abi {
+        unsafe {
+            mem::transmute(jit_type_get_abi(self.into()))
+        }
+    }
+    #[inline(always)]
+    /// Normalize this type to its basic numeric form, so native integers and
+    /// pointers become the fixed-size integer type of the same size
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// assert_eq!(get::<&'static u8>().normalize(), get::<isize>().normalize());
+    /// assert_eq!(get::<i32>().normalize(), typecs::get_int());
+    /// ```
+    pub fn normalize(&self) -> &Ty {
+        unsafe {
+            from_ptr(jit_type_normalize(self.into()))
+        }
+    }
+    #[inline(always)]
+    /// Get the type underneath any tags on this type
+    pub fn remove_tags(&self) -> &Ty {
+        unsafe {
+            from_ptr(jit_type_remove_tags(self.into()))
+        }
+    }
+    #[inline(always)]
+    /// Get the type this integer type would be promoted to when it is passed
+    /// to a function or used in arithmetic, so bytes and shorts become ints
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// assert_eq!(get::<u8>().promote_int(), typecs::get_uint());
+    /// assert_eq!(get::<i64>().promote_int(), typecs::get_long());
+    /// ```
+    pub fn promote_int(&self) -> &Ty {
+        unsafe {
+            from_ptr(jit_type_promote_int(self.into()))
+        }
+    }
+    #[inline(always)]
+    /// Check if a function returning this type has to return it through a
+    /// hidden pointer argument instead of in registers
+    pub fn return_via_pointer(&self) -> bool {
+        unsafe {
+            jit_type_return_via_pointer(self.into()) != 0
+        }
+    }
+    #[inline(always)]
+    /// Get the best alignment for values on this platform, which is the
+    /// alignment LibJIT uses for memory it allocates
+    pub fn best_alignment() -> usize {
+        unsafe {
+            jit_type_best_alignment() as usize
+        }
+    }
+    #[inline(always)]
+    fn get_tagged(&self) -> (c_int, &Ty) {
+        unsafe {
+            (jit_type_get_tagged_kind(self.into()), from_ptr(jit_type_get_tagged_type(self.into())))
+        }
+    }
+    #[inline(always)]
+    fn tag_data(&self) -> *mut c_void {
+        unsafe {
+            jit_type_get_tagged_data(self.into())
+        }
+    }
+    /// Check if this type has the same shape as `other`, comparing the kinds,
+    /// fields, parameters and tags of both instead of their identities.
+    ///
+    /// The data types are tagged with is compared by address, so types tagged
+    /// with data that was allocated separately, such as two names, differ.
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let f64_t = get::<f64>();
+    /// let a = Type::new_struct(&mut [&f64_t, &f64_t]);
+    /// let b = Type::new_struct(&mut [&f64_t, &f64_t]);
+    /// assert!(a != b);
+    /// assert!(a.structurally_eq(&b));
+    /// assert!(!a.structurally_eq(&Type::new_union(&mut [&f64_t, &f64_t])));
+    /// ```
+    pub fn structurally_eq(&self, other: &Ty) -> bool {
+        if self == other {
+            true
+        } else if self.get_kind() != other.get_kind() {
+            false
+        } else if self.is_tagged() {
+            let (kind, ty) = self.get_tagged();
+            let (other_kind, other_ty) = other.get_tagged();
+            kind == other_kind && self.tag_data() == other.tag_data()
+                && ty.structurally_eq(other_ty)
+        } else if self.is_pointer() {
+            self.get_ref().unwrap().structurally_eq(other.get_ref().unwrap())
+        } else if self.is_signature() {
+            self.abi() == other.abi()
+                && self.get_return().unwrap().structurally_eq(other.get_return().unwrap())
+                && self.params().count() == other.params().count()
+                && self.params().zip(other.params()).all(|(a, b)| a.structurally_eq(b))
+        } else if self.is_struct() || self.is_union() {
+            self.get_size() == other.get_size()
+                && self.get_alignment() == other.get_alignment()
+                && self.fields().count() == other.fields().count()
+                && self.fields().zip(other.fields()).all(|(a, b)|
+                    a.get_name() == b.get_name()
+                    && a.get_offset() == b.get_offset()
+                    && a.get_type().structurally_eq(b.get_type()))
+        } else {
+            true
+        }
+    }
+}
+impl Hash for Ty {
+    /// Hash the shape of this type, so types that are structurally equal hash
+    /// the same
+    fn hash<H>(&self, state: &mut H) where H:Hasher {
+        self.get_kind().bits().hash(state);
+        if self.is_tagged() {
+            let (kind, ty) = self.get_tagged();
+            kind.hash(state);
+            (self.tag_data() as usize).hash(state);
+            ty.hash(state);
+        } else if self.is_pointer() {
+            self.get_ref().unwrap().hash(state);
+        } else if self.is_signature() {
+            (self.abi() as u32).hash(state);
+            self.get_return().unwrap().hash(state);
+            for param in self.params() {
+                param.hash(state);
+            }
+        } else if self.is_struct() || self.is_union() {
+            self.get_size().hash(state);
+            for field in self.fields() {
+                field.get_name().hash(state);
+                field.get_offset().hash(state);
+                field.get_type().hash(state);
+            }
+        }
+    }
+}
+impl Hash for Type {
+    fn hash<H>(&self, state: &mut H) where H:Hasher {
+        self.deref().hash(state)
+    }
 }
 impl<'a> IntoIterator for &'a Ty {
     type IntoIter = Fields<'a>;
@@ -671,3 +1220,89 @@ impl<T> Deref for TaggedType<T> {
 pub fn get<'a, T>() -> CowType<'a> where T:Compile<'a> {
     <T as Compile>::get_type()
 }
+thread_local!(static TYPE_CACHE: RefCell<HashMap<TypeId, StaticType>> = RefCell::new(HashMap::new()));
+/// Keep the type descriptor given for the rest of the process, so it can be
+/// cached and borrowed without anything else freeing it
+fn leak_type(ty: Type) -> StaticType {
+    unsafe {
+        let leaked: StaticType = mem::transmute(ty._type);
+        mem::forget(ty);
+        leaked
+    }
+}
+/// Get the type descriptor cached on this thread for the type with the id
+/// given, making it with `make` the first time it is looked up
+///
+/// This is used by `Compile` implementations whose types are expensive to
+/// make, like derived structs and enums. The cached descriptor is borrowed,
+/// so it can't be changed, and `into_owned` gives a copy of it.
+pub fn cache_type<F>(id: TypeId, make: F) -> CowType<'static> where F:FnOnce() -> Type {
+    let cached = TYPE_CACHE.with(|cache| cache.borrow().get(&id).cloned());
+    match cached {
+        Some(ty) => ty.into(),
+        None => {
+            // `make` could look up other cached types, so don't hold the cache
+            let ty = leak_type(make());
+            TYPE_CACHE.with(|cache| cache.borrow_mut().insert(id, ty));
+            ty.into()
+        }
+    }
+}
+thread_local!(static SHAPE_CACHE: RefCell<HashMap<u64, Vec<(Vec<Type>, StaticType)>>> = RefCell::new(HashMap::new()));
+/// Get the type descriptor of the kind given cached on this thread for the
+/// parts given, making it with `make` the first time parts of the same shape
+/// are looked up
+///
+/// This is used by generic `Compile` implementations like tuples and function
+/// pointers, which have no single `TypeId`. The parts are compared by their
+/// shape, including the kinds and data of their tags, so they don't have to be
+/// cached themselves. Like `cache_type`, the descriptor is borrowed.
+///
+/// ```rust
+/// use jit::*;
+/// let f64_t = get::<f64>();
+/// let make = || Type::new_struct(&mut [&f64_t, &f64_t]);
+/// let pair_t = cache_shape(TypeKind::Struct, &[&f64_t, &f64_t], &make);
+/// assert_eq!(pair_t, cache_shape(TypeKind::Struct, &[&f64_t, &f64_t], &make));
+/// assert!(pair_t != cache_shape(TypeKind::Union, &[&f64_t, &f64_t], || Type::new_union(&mut [&f64_t, &f64_t])));
+/// let mut named_t = pair_t.into_owned();
+/// named_t.set_names(&["x", "y"]);
+/// assert!(cache_shape(TypeKind::Struct, &[&f64_t, &f64_t], &make).get_field("x").is_none());
+/// ```
+pub fn cache_shape<F>(kind: TypeKind, parts: &[&Ty], make: F) -> CowType<'static> where F:FnOnce() -> Type {
+    let mut hasher = SipHasher::new();
+    kind.bits().hash(&mut hasher);
+    for part in parts {
+        part.hash(&mut hasher);
+    }
+    let key = hasher.finish();
+    let cached = SHAPE_CACHE.with(|cache| cache.borrow().get(&key).and_then(|bucket| {
+        bucket.iter()
+            .find(|&&(ref other_parts, ty)| ty.get_kind() == kind
+                && other_parts.len() == parts.len()
+                && other_parts.iter().zip(parts.iter()).all(|(a, b)| a.structurally_eq(b)))
+            .map(|&(_, ty)| ty)
+    }));
+    match cached {
+        Some(ty) => ty.into(),
+        None => {
+            // `make` could look up other cached types, so don't hold the cache
+            let ty = leak_type(make());
+            let parts = parts.iter().map(|&part| part.to_owned()).collect();
+            SHAPE_CACHE.with(|cache| cache.borrow_mut().entry(key).or_insert_with(Vec::new).push((parts, ty)));
+            ty.into()
+        }
+    }
+}
+#[inline]
+/// Get the Rust type given as a type descriptor, reusing the same descriptor
+/// each time it is looked up on this thread
+///
+/// ```rust
+/// use jit::*;
+/// assert_eq!(get_cached::<&'static [f64]>(), get_cached::<&'static [f64]>());
+/// assert!(get::<&'static [f64]>() != get::<&'static [f64]>());
+/// ```
+pub fn get_cached<T>() -> CowType<'static> where T:Compile<'static> + Any {
+    cache_type(TypeId::of::<T>(), || get::<T>().into_owned())
+}
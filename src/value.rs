@@ -1,9 +1,11 @@
 use raw::*;
-use function::UncompiledFunction;
+use compile::Compile;
+use function::{is_unsigned, UncompiledFunction};
+use insn::Block;
 use types::*;
-use util::from_ptr;
+use util::{from_ptr, from_ptr_opt};
 use std::marker::PhantomData;
-use std::fmt;
+use std::{fmt, mem};
 use std::ops::*;
 /// Vals form the backbone of the storage system in `LibJIT`
 ///
@@ -67,13 +69,444 @@ impl Val {
             jit_value_set_addressable(self.into())
         }
     }
+    /// Determine if a value is a constant
+    #[inline]
+    pub fn is_constant(&self) -> bool {
+        unsafe {
+            jit_value_is_constant(self.into()) != 0
+        }
+    }
+    /// Determine if a value is local.  i.e. its scope extends over multiple
+    /// blocks within its function.
+    #[inline]
+    pub fn is_local(&self) -> bool {
+        unsafe {
+            jit_value_is_local(self.into()) != 0
+        }
+    }
+    /// Determine if a value is a function parameter
+    #[inline]
+    pub fn is_parameter(&self) -> bool {
+        unsafe {
+            jit_value_is_parameter(self.into()) != 0
+        }
+    }
+    /// Set a flag on a value to indicate that it is volatile.
+    /// The value is never cached in a register and every load and store of it
+    /// survives optimisation, which is needed for memory-mapped I/O.
+    #[inline]
+    pub fn set_volatile(&self) {
+        unsafe {
+            jit_value_set_volatile(self.into())
+        }
+    }
+    /// Determine if a value is volatile
+    #[inline]
+    pub fn is_volatile(&self) -> bool {
+        unsafe {
+            jit_value_is_volatile(self.into()) != 0
+        }
+    }
+    /// Create a reference to this value from the function given.
+    /// If the function is not the one the value was made in, or the value is
+    /// used in a different block, the value becomes a local instead of a
+    /// temporary.
+    #[inline]
+    pub fn add_ref(&self, func:&UncompiledFunction) {
+        unsafe {
+            jit_value_ref(func.into(), self.into())
+        }
+    }
+    /// Get the block which this temporary value was made in, or none if it
+    /// isn't a temporary
+    #[inline]
+    pub fn get_block(&self) -> Option<Block> {
+        unsafe {
+            from_ptr_opt(jit_value_get_block(self.into()))
+        }
+    }
+    /// Get the constant this value holds, or none if it isn't a numeric constant
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let mut ctx = Context::<()>::new();
+    /// let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> i64>());
+    /// assert_eq!(func.insn_of(42i64).as_constant(), Some(Constant::Long(42)));
+    /// assert_eq!(func.insn_of(1.5f32).as_constant(), Some(Constant::Float32(1.5)));
+    /// assert_eq!(Val::new(&func, &get::<i64>()).as_constant(), None);
+    /// ```
+    pub fn as_constant(&self) -> Option<Constant> {
+        if self.is_constant() {
+            unsafe {
+                Constant::from_raw(&mut jit_value_get_constant(self.into()))
+            }
+        } else {
+            None
+        }
+    }
+    /// Make a constant value of the type given, converting `value` to it
+    pub fn new_constant<'a>(func:&UncompiledFunction<'a>, value_type:&Ty, value:Constant) -> &'a Val {
+        unsafe {
+            let mut raw = value.to_raw();
+            let mut converted = mem::zeroed::<jit_constant_t>();
+            if jit_constant_convert(&mut converted, &raw, value_type.into(), 0) != 0 {
+                raw = converted;
+            } else {
+                panic!("Constant {:?} can't be converted to {:?}", value, value_type)
+            }
+            from_ptr(jit_value_create_constant(func.into(), &raw))
+        }
+    }
+    #[inline]
+    /// Make an instruction that converts this value to the Rust type `T`,
+    /// truncating it like `as` does if it doesn't fit
+    pub fn cast<'a, T>(&'a self, func:&UncompiledFunction<'a>) -> &'a Val where T:Compile<'a> {
+        func.insn_cast::<T>(self)
+    }
+    #[inline]
+    /// Make an instruction that loads the field with the name given from this
+    /// value, which can be a struct or a pointer to one
+    pub fn field<'a>(&'a self, func:&UncompiledFunction<'a>, name:&str) -> &'a Val {
+        func.insn_load_field(self, name)
+    }
+}
+/// A numeric constant held by a value
+///
+/// Bytes and shorts are held as `Int`s, and native integers and pointers are
+/// held as the fixed-size integer of the same size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Constant {
+    /// A signed 32-bit integer
+    Int(i32),
+    /// An unsigned 32-bit integer
+    UInt(u32),
+    /// A signed 64-bit integer
+    Long(i64),
+    /// An unsigned 64-bit integer
+    ULong(u64),
+    /// A 32-bit floating-point number
+    Float32(f32),
+    /// A 64-bit floating-point number
+    Float64(f64),
+    /// A native floating-point number
+    NFloat(f64)
+}
+macro_rules! fold_bin_op {
+    ($name:ident, $doc:expr, $int:ident, $uint:ident, $long:ident, $ulong:ident,
+     $float32:ident, $float64:ident, $nfloat:ident) => (
+        impl Constant {
+            #[doc=$doc]
+            pub fn $name(self, other: Constant) -> Option<Constant> {
+                use self::Constant::*;
+                unsafe {
+                    Some(match (self, other) {
+                        (Int(a), Int(b)) => Int($int(a, b)),
+                        (UInt(a), UInt(b)) => UInt($uint(a, b)),
+                        (Long(a), Long(b)) => Long($long(a, b)),
+                        (ULong(a), ULong(b)) => ULong($ulong(a, b)),
+                        (Float32(a), Float32(b)) => Float32($float32(a, b)),
+                        (Float64(a), Float64(b)) => Float64($float64(a, b)),
+                        (NFloat(a), NFloat(b)) => NFloat($nfloat(a, b)),
+                        _ => return None
+                    })
+                }
+            }
+        }
+    )
+}
+macro_rules! fold_checked_op {
+    ($name:ident, $doc:expr, $int:ident, $uint:ident, $long:ident, $ulong:ident,
+     $float32:ident, $float64:ident, $nfloat:ident) => (
+        impl Constant {
+            #[doc=$doc]
+            pub fn $name(self, other: Constant) -> Option<Constant> {
+                use self::Constant::*;
+                unsafe {
+                    let mut status = 1;
+                    let result = match (self, other) {
+                        (Int(a), Int(b)) => {
+                            let mut value = 0;
+                            status = $int(&mut value, a, b);
+                            Int(value)
+                        },
+                        (UInt(a), UInt(b)) => {
+                            let mut value = 0;
+                            status = $uint(&mut value, a, b);
+                            UInt(value)
+                        },
+                        (Long(a), Long(b)) => {
+                            let mut value = 0;
+                            status = $long(&mut value, a, b);
+                            Long(value)
+                        },
+                        (ULong(a), ULong(b)) => {
+                            let mut value = 0;
+                            status = $ulong(&mut value, a, b);
+                            ULong(value)
+                        },
+                        (Float32(a), Float32(b)) => Float32($float32(a, b)),
+                        (Float64(a), Float64(b)) => Float64($float64(a, b)),
+                        (NFloat(a), NFloat(b)) => NFloat($nfloat(a, b)),
+                        _ => return None
+                    };
+                    // anything but 1 means the division would have thrown
+                    if status == 1 {
+                        Some(result)
+                    } else {
+                        None
+                    }
+                }
+            }
+        }
+    )
+}
+macro_rules! fold_int_op {
+    ($name:ident, $doc:expr, $int:ident, $uint:ident, $long:ident, $ulong:ident) => (
+        impl Constant {
+            #[doc=$doc]
+            pub fn $name(self, other: Constant) -> Option<Constant> {
+                use self::Constant::*;
+                unsafe {
+                    Some(match (self, other) {
+                        (Int(a), Int(b)) => Int($int(a, b)),
+                        (UInt(a), UInt(b)) => UInt($uint(a, b)),
+                        (Long(a), Long(b)) => Long($long(a, b)),
+                        (ULong(a), ULong(b)) => ULong($ulong(a, b)),
+                        _ => return None
+                    })
+                }
+            }
+        }
+    )
+}
+macro_rules! fold_shift_op {
+    ($name:ident, $doc:expr, $int:ident, $uint:ident, $long:ident, $ulong:ident) => (
+        impl Constant {
+            #[doc=$doc]
+            pub fn $name(self, other: Constant) -> Option<Constant> {
+                use self::Constant::*;
+                unsafe {
+                    Some(match (self, other) {
+                        (Int(a), Int(b)) => Int($int(a, b as jit_uint)),
+                        (UInt(a), UInt(b)) => UInt($uint(a, b)),
+                        (Long(a), Long(b)) => Long($long(a, b as jit_uint)),
+                        (ULong(a), ULong(b)) => ULong($ulong(a, b as jit_uint)),
+                        _ => return None
+                    })
+                }
+            }
+        }
+    )
+}
+impl Constant {
+    /// Get the constant held by the raw constant given
+    unsafe fn from_raw(raw: &mut jit_constant_t) -> Option<Constant> {
+        let kind = jit_type_get_kind(jit_type_normalize(raw._type));
+        let kind = TypeKind::from_bits_truncate(kind);
+        Some(if kind == TypeKind::SByte || kind == TypeKind::UByte || kind == TypeKind::Short
+            || kind == TypeKind::UShort || kind == TypeKind::Int {
+            Constant::Int(*raw.un.int_value())
+        } else if kind == TypeKind::UInt {
+            Constant::UInt(*raw.un.uint_value())
+        } else if kind == TypeKind::Long {
+            Constant::Long(*raw.un.long_value())
+        } else if kind == TypeKind::ULong {
+            Constant::ULong(*raw.un.ulong_value())
+        } else if kind == TypeKind::Float32 {
+            Constant::Float32(*raw.un.float32_value())
+        } else if kind == TypeKind::Float64 {
+            Constant::Float64(*raw.un.float64_value())
+        } else if kind == TypeKind::NFloat {
+            Constant::NFloat(*raw.un.nfloat_value())
+        } else {
+            return None
+        })
+    }
+    /// Get the raw constant that holds this
+    fn to_raw(self) -> jit_constant_t {
+        unsafe {
+            let mut raw = mem::zeroed::<jit_constant_t>();
+            raw._type = self.get_type().into();
+            match self {
+                Constant::Int(v) => *raw.un.int_value() = v,
+                Constant::UInt(v) => *raw.un.uint_value() = v,
+                Constant::Long(v) => *raw.un.long_value() = v,
+                Constant::ULong(v) => *raw.un.ulong_value() = v,
+                Constant::Float32(v) => *raw.un.float32_value() = v,
+                Constant::Float64(v) => *raw.un.float64_value() = v,
+                Constant::NFloat(v) => *raw.un.nfloat_value() = v
+            }
+            raw
+        }
+    }
+    /// Get the type this constant is held as
+    pub fn get_type(self) -> StaticType {
+        match self {
+            Constant::Int(_) => consts::get_int(),
+            Constant::UInt(_) => consts::get_uint(),
+            Constant::Long(_) => consts::get_long(),
+            Constant::ULong(_) => consts::get_ulong(),
+            Constant::Float32(_) => consts::get_float32(),
+            Constant::Float64(_) => consts::get_float64(),
+            Constant::NFloat(_) => consts::get_nfloat()
+        }
+    }
+    /// Convert this constant to the type given, or none if it can't be or it
+    /// overflows when `overflow_check` is set
+    ///
+    /// ```rust
+    /// use jit::*;
+    /// let big = Constant::Long(1 << 40);
+    /// assert_eq!(big.convert(typecs::get_float64(), false), Some(Constant::Float64((1u64 << 40) as f64)));
+    /// assert_eq!(big.convert(typecs::get_int(), true), None);
+    /// ```
+    pub fn convert(self, ty:&Ty, overflow_check: bool) -> Option<Constant> {
+        unsafe {
+            let raw = self.to_raw();
+            let mut converted = mem::zeroed::<jit_constant_t>();
+            if jit_constant_convert(&mut converted, &raw, ty.into(), overflow_check as i32) != 0 {
+                Constant::from_raw(&mut converted)
+            } else {
+                None
+            }
+        }
+    }
+    /// Check if this constant is less than zero
+    pub fn is_negative(self) -> bool {
+        match self {
+            Constant::Int(v) => v < 0,
+            Constant::Long(v) => v < 0,
+            Constant::Float32(v) => v < 0.0,
+            Constant::Float64(v) | Constant::NFloat(v) => v < 0.0,
+            Constant::UInt(_) | Constant::ULong(_) => false
+        }
+    }
+    /// Negate this constant
+    pub fn neg(self) -> Constant {
+        use self::Constant::*;
+        unsafe {
+            match self {
+                Int(v) => Int(jit_int_neg(v)),
+                UInt(v) => UInt(jit_uint_neg(v)),
+                Long(v) => Long(jit_long_neg(v)),
+                ULong(v) => ULong(jit_ulong_neg(v)),
+                Float32(v) => Float32(jit_float32_neg(v)),
+                Float64(v) => Float64(jit_float64_neg(v)),
+                NFloat(v) => NFloat(jit_nfloat_neg(v))
+            }
+        }
+    }
+}
+fold_bin_op!(add, "Add this constant to another of the same kind",
+    jit_int_add, jit_uint_add, jit_long_add, jit_ulong_add,
+    jit_float32_add, jit_float64_add, jit_nfloat_add);
+fold_bin_op!(sub, "Subtract another constant of the same kind from this",
+    jit_int_sub, jit_uint_sub, jit_long_sub, jit_ulong_sub,
+    jit_float32_sub, jit_float64_sub, jit_nfloat_sub);
+fold_bin_op!(mul, "Multiply this constant by another of the same kind",
+    jit_int_mul, jit_uint_mul, jit_long_mul, jit_ulong_mul,
+    jit_float32_mul, jit_float64_mul, jit_nfloat_mul);
+fold_checked_op!(div, "Divide this constant by another of the same kind, or none if \
+the divisor is zero or the quotient overflows",
+    jit_int_div, jit_uint_div, jit_long_div, jit_ulong_div,
+    jit_float32_div, jit_float64_div, jit_nfloat_div);
+fold_checked_op!(rem, "Find the remainder when this constant is divided by another of \
+the same kind, or none if the divisor is zero or the quotient overflows",
+    jit_int_rem, jit_uint_rem, jit_long_rem, jit_ulong_rem,
+    jit_float32_rem, jit_float64_rem, jit_nfloat_rem);
+fold_int_op!(bitand, "Do a bitwise and of this integer constant with another of the same kind",
+    jit_int_and, jit_uint_and, jit_long_and, jit_ulong_and);
+fold_int_op!(bitor, "Do a bitwise or of this integer constant with another of the same kind",
+    jit_int_or, jit_uint_or, jit_long_or, jit_ulong_or);
+fold_int_op!(bitxor, "Do a bitwise xor of this integer constant with another of the same kind",
+    jit_int_xor, jit_uint_xor, jit_long_xor, jit_ulong_xor);
+fold_shift_op!(shl, "Shift this integer constant left by another of the same kind",
+    jit_int_shl, jit_uint_shl, jit_long_shl, jit_ulong_shl);
+fold_shift_op!(shr, "Shift this integer constant right by another of the same kind, \
+shifting in the sign bit if it is signed",
+    jit_int_shr, jit_uint_shr, jit_long_shr, jit_ulong_shr);
+/// Builds a struct value by storing each of its fields by name, then checks
+/// every field has been stored
+///
+/// ```rust
+/// use jit::*;
+/// let mut ctx = Context::<()>::new();
+/// let func = UncompiledFunction::new(&mut ctx, &get::<fn() -> f64>());
+/// let mut pos_t = Type::new_struct(&mut [&get::<f64>(), &get::<f64>()]);
+/// pos_t.set_names(&["x", "y"]);
+/// let pos = StructBuilder::new(&func, &pos_t)
+///     .field("x", func.insn_of(3.0f64))
+///     .field("y", func.insn_of(4.0f64))
+///     .build();
+/// func.insn_return(pos.field(&func, "y"));
+/// ```
+pub struct StructBuilder<'a, 'b> where 'a:'b {
+    func: &'b UncompiledFunction<'a>,
+    ty: &'b Ty,
+    value: &'a Val,
+    addr: &'a Val,
+    stored: Vec<bool>
+}
+impl<'a, 'b> StructBuilder<'a, 'b> {
+    /// Make a builder for a new value of the struct type given
+    pub fn new(func:&'b UncompiledFunction<'a>, ty:&'b Ty) -> StructBuilder<'a, 'b> {
+        if !ty.is_struct() {
+            panic!("Type given to StructBuilder should be struct, got {:?}", ty);
+        }
+        let value = Val::new(func, ty);
+        StructBuilder {
+            func: func,
+            ty: ty,
+            value: value,
+            addr: func.insn_address_of(value),
+            stored: vec![false; ty.fields().count()]
+        }
+    }
+    /// Store the value given in the field with the name given, checking it has
+    /// the right type
+    pub fn field(mut self, name:&str, value:&'a Val) -> StructBuilder<'a, 'b> {
+        match self.ty.get_field(name) {
+            Some(field) => self.stored[field.index as usize] = true,
+            None => panic!("No field {} on {:?}", name, self.ty)
+        }
+        self.func.insn_store_field(self.addr, name, value);
+        self
+    }
+    /// Get the struct value, checking every field has been stored
+    pub fn build(self) -> &'a Val {
+        for (field, stored) in self.ty.fields().zip(self.stored.iter()) {
+            if !stored {
+                panic!("Field {} of {:?} was never stored", field.get_name().unwrap_or("?"), self.ty);
+            }
+        }
+        self.value
+    }
+}
+/// A value that can be given to an instruction, which is either a `&Val` or
+/// a Rust value that is compiled into a constant
+pub trait Operand<'a> {
+    /// Get this as a value in the function given
+    fn into_val(self, func:&UncompiledFunction<'a>) -> &'a Val;
+}
+impl<'a> Operand<'a> for &'a Val {
+    #[inline(always)]
+    fn into_val(self, _:&UncompiledFunction<'a>) -> &'a Val {
+        self
+    }
+}
+impl<'a, T> Operand<'a> for T where T:Compile<'a> {
+    #[inline(always)]
+    fn into_val(self, func:&UncompiledFunction<'a>) -> &'a Val {
+        func.insn_of(self)
+    }
 }
 macro_rules! bin_op {
     ($trait_ty:ident, $trait_func:ident, $func:ident) => (
-        impl<'a> $trait_ty<&'a Val> for &'a Val {
+        impl<'a, T> $trait_ty<T> for &'a Val where T:Operand<'a> {
             type Output = &'a Val;
-            fn $trait_func(self, other: &'a Val) -> &'a Val {
-                self.get_function().$func(self, other)
+            fn $trait_func(self, other: T) -> &'a Val {
+                let func = self.get_function();
+                func.$func(self, other.into_val(&func))
             }
         }
     )
@@ -100,3 +533,162 @@ bin_op!{Shr, shr, insn_shr}
 bin_op!{Sub, sub, insn_sub}
 un_op!{Neg, neg, insn_neg}
 un_op!{Not, not, insn_not}
+macro_rules! val_ext {
+    ($($bin_name:ident => $bin_func:ident),+; $($un_name:ident => $un_func:ident),+) => (
+        impl<'a> ValExt<'a> for &'a Val {
+            $(
+                #[inline]
+                fn $bin_name<T>(self, other: T) -> &'a Val where T:Operand<'a> {
+                    let func = self.get_function();
+                    func.$bin_func(self, other.into_val(&func))
+                }
+            )+
+            $(
+                #[inline]
+                fn $un_name(self) -> &'a Val {
+                    self.get_function().$un_func(self)
+                }
+            )+
+        }
+    )
+}
+/// Methods on values that make instructions in the function the value is in,
+/// so generators can be written like normal Rust
+///
+/// ```rust
+/// # #[no_link] #[macro_use] extern crate jit_macros;
+/// # extern crate jit;
+/// use jit::*;
+/// # fn main() {
+/// let mut ctx = Context::<()>::new();
+/// jit_func!(&mut ctx, func, fn(x: f64) -> f64 {
+///     func.insn_if(x.lt(0f64), || func.insn_return(-x));
+///     func.insn_return((x * 2f64 + 1f64).sqrt().min(x));
+/// }, {
+///     assert_eq!(func(-3.0), 3.0);
+///     assert_eq!(func(4.0), 3.0);
+/// });
+/// # }
+/// ```
+pub trait ValExt<'a>: Sized {
+    /// Make an instruction that checks if this value is lower than the other
+    fn lt<T>(self, other: T) -> &'a Val where T:Operand<'a>;
+    /// Make an instruction that checks if this value is lower than or equal to the other
+    fn le<T>(self, other: T) -> &'a Val where T:Operand<'a>;
+    /// Make an instruction that checks if this value is greater than the other
+    fn gt<T>(self, other: T) -> &'a Val where T:Operand<'a>;
+    /// Make an instruction that checks if this value is greater than or equal to the other
+    fn ge<T>(self, other: T) -> &'a Val where T:Operand<'a>;
+    /// Make an instruction that checks if this value is equal to the other
+    fn eq_<T>(self, other: T) -> &'a Val where T:Operand<'a>;
+    /// Make an instruction that checks if this value is not equal to the other
+    fn ne_<T>(self, other: T) -> &'a Val where T:Operand<'a>;
+    /// Make an instruction that gets the lower of this value and the other
+    fn min<T>(self, other: T) -> &'a Val where T:Operand<'a>;
+    /// Make an instruction that gets the greater of this value and the other
+    fn max<T>(self, other: T) -> &'a Val where T:Operand<'a>;
+    /// Make an instruction that raises this value to the power of the other
+    fn pow<T>(self, other: T) -> &'a Val where T:Operand<'a>;
+    /// Make an instruction that gets the square root of this value
+    fn sqrt(self) -> &'a Val;
+    /// Make an instruction that gets the absolute value of this value
+    fn abs(self) -> &'a Val;
+    /// Make an instruction that gets the sign of this value
+    fn sign(self) -> &'a Val;
+    /// Make an instruction that rounds this value down
+    fn floor(self) -> &'a Val;
+    /// Make an instruction that rounds this value up
+    fn ceil(self) -> &'a Val;
+    /// Make an instruction that rounds this value to the nearest integer
+    fn round(self) -> &'a Val;
+    /// Make an instruction that rounds this value towards zero
+    fn trunc(self) -> &'a Val;
+    /// Make an instruction that checks if this value is NaN
+    fn is_nan(self) -> &'a Val;
+    /// Make an instruction that checks if this value is non-zero
+    fn to_bool(self) -> &'a Val;
+}
+val_ext!{
+    lt => insn_lt,
+    le => insn_leq,
+    gt => insn_gt,
+    ge => insn_geq,
+    eq_ => insn_eq,
+    ne_ => insn_neq,
+    min => insn_min,
+    max => insn_max,
+    pow => insn_pow;
+    sqrt => insn_sqrt,
+    abs => insn_abs,
+    sign => insn_sign,
+    floor => insn_floor,
+    ceil => insn_ceil,
+    round => insn_round,
+    trunc => insn_trunc,
+    is_nan => insn_is_nan,
+    to_bool => insn_to_bool
+}
+/// A mutable local variable that always holds values of the Rust type `T`
+///
+/// Reading it with `get` gives a copy of its current value, so values read
+/// before it is set again keep their old value.
+///
+/// LibJIT promotes arithmetic on integers smaller than `i32` to `i32` or `u32`,
+/// so integers given to `set` are converted to the variable's type when they
+/// have the same signedness as it. Any integer can be stored in a `bool`
+/// variable, so the results of comparisons can be.
+///
+/// ```rust
+/// use jit::*;
+/// let mut ctx = Context::<()>::new();
+/// let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+/// let n = func.param(0).unwrap();
+/// let total = func.var::<i64>(1);
+/// let before = total.get();
+/// total.set(total.get() * n);
+/// func.insn_return(total.get() - before);
+/// ```
+pub struct Var<'a, 'b, T> where 'a:'b {
+    func: &'b UncompiledFunction<'a>,
+    value: &'a Val,
+    marker: PhantomData<T>
+}
+impl<'a, 'b, T> Var<'a, 'b, T> where 'a:'b, T:Compile<'a> {
+    /// Make a new variable in the function given that starts with the value given
+    pub fn new<I>(func:&'b UncompiledFunction<'a>, init: I) -> Var<'a, 'b, T> where I:Operand<'a> {
+        let var = Var {
+            func: func,
+            value: Val::new(func, &get::<T>()),
+            marker: PhantomData
+        };
+        var.set(init);
+        var
+    }
+    #[inline]
+    /// Make an instruction that copies the current value of the variable
+    pub fn get(&self) -> &'a Val {
+        self.func.insn_load(self.value)
+    }
+    /// Make an instruction that stores the value given in the variable,
+    /// panicking if it doesn't have the variable's type and isn't an integer
+    /// that can be converted to it, even in release builds
+    pub fn set<O>(&self, value: O) where O:Operand<'a> {
+        let value = value.into_val(self.func);
+        let value_t = value.get_type();
+        let var_t = self.value.get_type();
+        let value = if value_t.remove_tags().structurally_eq(var_t.remove_tags()) {
+            value
+        } else if value_t.remove_tags().is_int() && var_t.remove_tags().is_int()
+                  && (is_unsigned(value_t) == is_unsigned(var_t) || var_t == consts::get_sys_bool()) {
+            self.func.insn_convert(value, var_t, false)
+        } else {
+            panic!("Value given to Var::set should be {:?}, got {:?}", var_t, value_t);
+        };
+        self.func.insn_store(self.value, value);
+    }
+    #[inline(always)]
+    /// Get the local value that holds the variable
+    pub fn as_val(&self) -> &'a Val {
+        self.value
+    }
+}
@@ -0,0 +1,100 @@
+use raw::*;
+use context::Context;
+use function::Func;
+use types::{StructLayout, Ty, Type};
+use util::from_ptr_opt;
+use libc::c_void;
+use std::marker::PhantomData;
+/// Builds a `VTable` out of compiled functions, one slot per method
+///
+/// ```rust
+/// use jit::*;
+/// let mut ctx = Context::<()>::new();
+/// {
+///     let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+///     func.insn_return(func.param(0).unwrap());
+///     func.compile();
+/// }
+/// let identity = ctx.functions().next().unwrap();
+/// let vtable = VTableBuilder::new()
+///     .method("identity", identity)
+///     .build();
+/// assert_eq!(vtable.get_type().get_field("identity").unwrap().get_offset(), 0);
+/// assert!(vtable.get_function(&ctx, 0) == Some(identity));
+/// ```
+pub struct VTableBuilder<'a> {
+    methods: Vec<(&'a str, &'a Func)>
+}
+impl<'a> VTableBuilder<'a> {
+    /// Make a new builder with no methods
+    pub fn new() -> VTableBuilder<'a> {
+        VTableBuilder {
+            methods: Vec::new()
+        }
+    }
+    /// Add a method with the name given, which is placed in the slot after
+    /// the last method
+    pub fn method(mut self, name: &'a str, func: &'a Func) -> VTableBuilder<'a> {
+        if !func.is_compiled() {
+            panic!("Function given for method {} should be compiled", name)
+        }
+        self.methods.push((name, func));
+        self
+    }
+    /// Make the table, laying it out as a struct with a pointer to each
+    /// method's signature for each slot
+    pub fn build(&self) -> VTable<'a> {
+        let slot_types = self.methods.iter()
+            .map(|&(_, func)| Type::new_pointer(func.get_signature()))
+            .collect::<Vec<_>>();
+        let ty = self.methods.iter().zip(slot_types.iter())
+            .fold(StructLayout::new(), |layout, (&(name, _), ty)| layout.field(name, ty))
+            .build();
+        let table = self.methods.iter()
+            .map(|&(_, func)| unsafe { jit_function_to_vtable_pointer(func.into()) })
+            .collect::<Vec<_>>();
+        VTable {
+            ty: ty,
+            table: table.into_boxed_slice(),
+            marker: PhantomData
+        }
+    }
+}
+/// A table of pointers to compiled functions, which objects point to so the
+/// methods called on them can be chosen at runtime with `insn_call_vtable`
+///
+/// The table lives as long as the value, so it must outlive any objects that
+/// point to it.
+pub struct VTable<'a> {
+    ty: Type,
+    table: Box<[*mut c_void]>,
+    marker: PhantomData<&'a Func>
+}
+impl<'a> VTable<'a> {
+    #[inline(always)]
+    /// Get the struct type the table is laid out with, which has a field for
+    /// each slot
+    pub fn get_type(&self) -> &Ty {
+        &self.ty
+    }
+    #[inline(always)]
+    /// Get a pointer to the table, which should be stored as the first field
+    /// of the objects that use it
+    ///
+    /// The pointer dangles once this is dropped, so keep the `VTable` alive
+    /// for as long as any object that stores it can have methods called on it.
+    pub fn as_ptr(&self) -> *const c_void {
+        self.table.as_ptr() as *const c_void
+    }
+    #[inline(always)]
+    /// Get the number of slots in the table
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+    /// Get the function in the slot given from the context it was compiled in
+    pub fn get_function<'b, T>(&self, ctx: &'b Context<T>, slot: usize) -> Option<&'b Func> {
+        self.table.get(slot).and_then(|&ptr| unsafe {
+            from_ptr_opt(jit_function_from_vtable_pointer(ctx.into(), ptr))
+        })
+    }
+}
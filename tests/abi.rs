@@ -0,0 +1,114 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_raw_push_pop() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+    let x = func.param(0).unwrap();
+    unsafe {
+        func.raw_call()
+            .push(x)
+            .push(x)
+            .pop_stack(1)
+            .defer_pop_stack(1)
+            .flush_defer_pop(0);
+    }
+    func.insn_return(x + 1i64);
+    func.compile().with(|func: extern fn(i64) -> i64| {
+        assert_eq!(func(41), 42);
+    });
+}
+
+extern fn halve(x: f64) -> f64 {
+    x / 2.0
+}
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+#[test]
+fn test_windows_abis_are_cdecl() {
+    for &abi in [Abi::StdCall, Abi::FastCall].iter() {
+        let f64_t = get::<f64>();
+        let sig = Type::new_signature(abi, &f64_t, &mut [&f64_t]);
+        assert_eq!(sig.abi(), abi);
+        let mut ctx = Context::<()>::new();
+        let func = UncompiledFunction::new(&mut ctx, &sig);
+        let x = func.param(0).unwrap();
+        let halved = func.insn_call_native1(Some("halve"), halve, &sig, [x], flags::CallFlags::NO_THROW);
+        func.insn_return(halved + 1.0f64);
+        // called with the C calling convention since the others are no-ops here
+        func.compile().with(|func: extern "C" fn(f64) -> f64| {
+            assert_eq!(func(5.0), 3.5);
+        });
+    }
+}
+
+// These rely on details of LibJIT's x86-64 back end that it doesn't document,
+// like its register numbers and when it reserves stack space for outgoing
+// parameters, so they only run with the `backend_tests` feature.
+#[cfg(all(feature = "backend_tests", target_arch = "x86_64", target_os = "linux"))]
+mod backend {
+    use jit::*;
+
+    // the x86-64 back end's numbers for the registers used below
+    const RAX: i32 = 0;
+    const RDI: i32 = 5;
+
+    extern fn double(x: i64) -> i64 {
+        x * 2
+    }
+
+    #[test]
+    fn test_raw_registers() {
+        let mut ctx = Context::<()>::new();
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+        let i64_t = get::<i64>();
+        let arg = Val::new(&func, &i64_t);
+        let doubled = Val::new(&func, &i64_t);
+        let result = unsafe {
+            func.raw_call().incoming_reg(arg, RDI);
+            // declared without parameters so only the raw instruction passes `arg`
+            let double: extern fn() -> i64 = ::std::mem::transmute(double as extern fn(i64) -> i64);
+            func.raw_call().outgoing_reg(arg, RDI);
+            let result = func.insn_call_native0(Some("double"), double, &get::<fn() -> i64>(), flags::CallFlags::NO_THROW);
+            func.raw_call().return_reg(doubled, RAX);
+            result
+        };
+        func.insn_return(result * 10i64 + doubled);
+        func.compile().with(|func: extern fn(i64) -> i64| {
+            assert_eq!(func(4), 88);
+            assert_eq!(func(-1), -22);
+        });
+    }
+
+    extern fn sum8(a: i64, b: i64, c: i64, d: i64, e: i64, f: i64, g: i64, h: i64) -> i64 {
+        a + b + c + d + e + f + g + h
+    }
+    extern fn seventh(_: i64, _: i64, _: i64, _: i64, _: i64, _: i64, g: i64) -> i64 {
+        g
+    }
+
+    #[test]
+    fn test_raw_set_param() {
+        let mut ctx = Context::<()>::new();
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(i64) -> i64>());
+        let x = func.param(0).unwrap();
+        let i64_t = get::<i64>();
+        let flags = flags::CallFlags::NO_THROW;
+        // calling sum8 makes room for two parameters on the stack
+        let sig8 = Type::new_signature(Abi::CDecl, &i64_t, &mut [&i64_t, &i64_t, &i64_t, &i64_t, &i64_t, &i64_t, &i64_t, &i64_t]);
+        let mut args8: Vec<_> = (1..9).map(|i: i64| func.insn_of(i)).collect();
+        let sum = func.insn_call_indirect(func.insn_of(sum8 as usize), &sig8, &mut args8, flags);
+        // seventh is declared with six parameters so only set_param passes the last
+        let sig6 = Type::new_signature(Abi::CDecl, &i64_t, &mut [&i64_t, &i64_t, &i64_t, &i64_t, &i64_t, &i64_t]);
+        let mut args6: Vec<_> = (0..6).map(|_| func.insn_of(0i64)).collect();
+        unsafe {
+            func.raw_call().set_param(x, 0);
+        }
+        let last = func.insn_call_indirect(func.insn_of(seventh as usize), &sig6, &mut args6, flags);
+        func.insn_return(sum + last);
+        func.compile().with(|func: extern fn(i64) -> i64| {
+            assert_eq!(func(100), 136);
+        });
+    }
+}
@@ -0,0 +1,91 @@
+#![feature(plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+use jit::*;
+use std::f64::NAN;
+
+#[test]
+fn test_rem_ieee() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: f64, y: f64) -> f64 {
+        func.insn_return(func.insn_rem_ieee(x, y));
+    }, {
+        assert_eq!(func(5.0, 3.0), -1.0);
+        assert_eq!(func(4.0, 3.0), 1.0);
+        assert_eq!(func(-5.0, 3.0), 1.0);
+    });
+}
+
+#[test]
+fn test_ordered_compare() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: f64, y: f64) -> i32 {
+        func.insn_return(func.insn_cmpl(x, y));
+    }, {
+        assert_eq!(func(1.0, 2.0), -1);
+        assert_eq!(func(2.0, 2.0), 0);
+        assert_eq!(func(3.0, 2.0), 1);
+        assert_eq!(func(NAN, 2.0), -1);
+    });
+    jit_func!(&mut ctx, func, fn(x: f64, y: f64) -> i32 {
+        func.insn_return(func.insn_cmpg(x, y));
+    }, {
+        assert_eq!(func(1.0, 2.0), -1);
+        assert_eq!(func(3.0, 2.0), 1);
+        assert_eq!(func(2.0, NAN), 1);
+    });
+}
+
+#[test]
+fn test_sshr() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: u32) -> u32 {
+        func.insn_return(func.insn_sshr(x, func.insn_of(4u32)));
+    }, {
+        assert_eq!(func(0x8000_0000), 0xF800_0000);
+        assert_eq!(func(0x10), 1);
+    });
+}
+
+#[test]
+fn test_add_relative() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(pair: &'static (u32, u32)) -> u32 {
+        let second = func.insn_add_relative(pair, 4);
+        func.insn_return(func.insn_load_relative(second, 0, &get::<u32>()));
+    }, {
+        static PAIR: (u32, u32) = (3, 7);
+        assert_eq!(func(&PAIR), 7);
+    });
+}
+
+#[test]
+#[should_panic]
+fn test_add_relative_not_pointer() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(u32) -> u32>());
+    func.insn_add_relative(&func[0], 4);
+}
+
+#[test]
+fn test_mixed_operands() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: i32, y: i32) -> i32 {
+        func.insn_if(x.eq_(y), || func.insn_return(0i32.compile(func)));
+        func.insn_return((x * 3i32 + y - 1i32) % 7i32);
+    }, {
+        assert_eq!(func(2, 2), 0);
+        assert_eq!(func(2, 5), 3);
+    });
+    jit_func!(&mut ctx, func, fn(x: f64) -> f64 {
+        func.insn_return(x.abs().sqrt().max(1f64).pow(2f64));
+    }, {
+        assert_eq!(func(-16.0), 16.0);
+        assert_eq!(func(0.25), 1.0);
+    });
+    jit_func!(&mut ctx, func, fn(x: u32) -> u32 {
+        func.insn_return(((x << 4u32) | 1u32) & 0xffu32);
+    }, assert_eq!(func(0x1f), 0xf1));
+}
@@ -0,0 +1,122 @@
+#![feature(plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+extern crate libc;
+use jit::*;
+use libc::c_void;
+use std::default::Default;
+
+macro_rules! assert_casts(
+    ($func:ident, $from:ty, $to:ty, [$($value:expr),+]) => ({
+        $({
+            let value: $from = $value;
+            assert_eq!($func(value), value as $to);
+        })+
+    });
+);
+macro_rules! test_cast_matrix(
+    ($test_name:ident, $from:ty, $values:tt) => (
+        #[test]
+        fn $test_name() {
+            let mut ctx = Context::<()>::new();
+            test_cast_matrix!(&mut ctx, $from, $values => i8, u8, i16, u16, i32, u32, i64, u64, isize, usize, f32, f64);
+        }
+    );
+    ($ctx:expr, $from:ty, $values:tt => $($to:ty),+) => (
+        $(jit_func!($ctx, func, fn(x: $from) -> $to {
+            func.insn_return(func.insn_cast::<$to>(x));
+        }, assert_casts!(func, $from, $to, $values));)+
+    );
+);
+test_cast_matrix!(test_cast_i8, i8, [0, 1, 42, 100, !0]);
+test_cast_matrix!(test_cast_u8, u8, [0, 1, 42, 100, !0]);
+test_cast_matrix!(test_cast_i16, i16, [0, 1, 42, 100, !0]);
+test_cast_matrix!(test_cast_u16, u16, [0, 1, 42, 100, !0]);
+test_cast_matrix!(test_cast_i32, i32, [0, 1, 42, 100, !0]);
+test_cast_matrix!(test_cast_u32, u32, [0, 1, 42, 100, !0]);
+test_cast_matrix!(test_cast_i64, i64, [0, 1, 42, 100, !0]);
+test_cast_matrix!(test_cast_u64, u64, [0, 1, 42, 100, !0]);
+test_cast_matrix!(test_cast_isize, isize, [0, 1, 42, 100, !0]);
+test_cast_matrix!(test_cast_usize, usize, [0, 1, 42, 100, !0]);
+test_cast_matrix!(test_cast_f32, f32, [0.0, 1.0, 42.5, 100.25]);
+test_cast_matrix!(test_cast_f64, f64, [0.0, 1.0, 42.5, 100.25]);
+macro_rules! assert_casts_checked(
+    ($ctx:expr, $from:ty => $to:ty, [$($value:expr => $result:expr),+]) => ({
+        let func = UncompiledFunction::new($ctx, &get::<fn($from) -> $to>());
+        func.insn_return(func.insn_cast_checked::<$to>(func.param(0).unwrap()));
+        let func = func.compile();
+        $({
+            let mut value: $from = $value;
+            let mut result: $to = Default::default();
+            let status = unsafe {
+                func.apply(&mut [&mut value as *mut $from as *mut c_void], &mut result as *mut $to as *mut c_void)
+            };
+            assert_eq!(status.map(|()| result), $result);
+        })+
+    });
+);
+
+#[test]
+fn test_cast_checked() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: i32) -> u8 {
+        func.insn_return(x.cast::<u8>(func));
+    }, assert_eq!(func(300), 44));
+    jit_func!(&mut ctx, func, fn(x: i32) -> i32 {
+        func.insn_return(func.insn_convert(func.insn_cast_checked::<u8>(x), &get::<i32>(), false));
+    }, assert_eq!(func(200), 200));
+    assert_casts_checked!(&mut ctx, i32 => u8, [
+        255 => Ok(255),
+        256 => Err(BuiltinException::Overflow),
+        -1 => Err(BuiltinException::Overflow)
+    ]);
+    assert_casts_checked!(&mut ctx, i64 => i32, [
+        -5 => Ok(-5),
+        1 << 40 => Err(BuiltinException::Overflow)
+    ]);
+    assert_casts_checked!(&mut ctx, f64 => i32, [
+        -2.5 => Ok(-2),
+        1e10 => Err(BuiltinException::Overflow),
+        -1e10 => Err(BuiltinException::Overflow)
+    ]);
+    assert_casts_checked!(&mut ctx, f64 => u32, [
+        42.5 => Ok(42),
+        -1.0 => Err(BuiltinException::Overflow),
+        5e9 => Err(BuiltinException::Overflow)
+    ]);
+}
+
+#[test]
+fn test_to_bool() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: f64) -> i32 {
+        func.insn_return(func.insn_to_bool(x));
+    }, {
+        assert_eq!(func(0.0), 0);
+        assert_eq!(func(2.5), 1);
+    });
+    jit_func!(&mut ctx, func, fn(x: i64) -> i32 {
+        func.insn_return(func.insn_to_not_bool(x));
+    }, {
+        assert_eq!(func(0), 1);
+        assert_eq!(func(-7), 0);
+    });
+}
+
+#[test]
+#[should_panic]
+fn test_cast_struct() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn((i32, i32)) -> i32>());
+    func.insn_cast::<i32>(&func[0]);
+}
+
+#[test]
+#[should_panic(expected = "to convert to bool")]
+fn test_to_bool_struct() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn((i32, i32)) -> i32>());
+    func.insn_to_bool(&func[0]);
+}
@@ -6,6 +6,7 @@ extern crate jit;
 extern crate test;
 use jit::*;
 use std::default::Default;
+use std::i32;
 macro_rules! test_compile(
     ($ty:ty, $test_name:ident, $id:ident, $kind:ident) => (
         #[test]
@@ -61,3 +62,66 @@ impl Default for Kind {
     }
 }
 test_compile_adv!(Kind, test_compile_repr_enum, SByte);
+#[test]
+fn test_constant_folding() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: u8) -> u8 {
+        func.set_folding(true);
+        let two = func.insn_of(2u8);
+        let sum = two + func.insn_of(250u8);
+        assert_eq!(sum.as_constant(), Some(Constant::UInt(252)));
+        assert_eq!(sum.get_type(), typecs::get_uint());
+        assert!(!(x + two).is_constant());
+        func.insn_return(func.insn_convert(sum - func.insn_of(2u8), &get::<u8>(), false));
+    }, assert_eq!(func(0), 250));
+    jit_func!(&mut ctx, func, fn() -> f64 {
+        func.set_folding(true);
+        let area = func.insn_of(2.5f64) * func.insn_of(4f64);
+        assert_eq!(area.as_constant(), Some(Constant::Float64(10.0)));
+        func.insn_return(-area);
+    }, assert_eq!(func(), -10.0));
+    jit_func!(&mut ctx, func, fn() -> i32 {
+        func.set_folding(true);
+        let (a, b) = (func.insn_of(-7i32), func.insn_of(2i32));
+        assert_eq!((a / b).as_constant(), Some(Constant::Int(-3)));
+        assert_eq!((a % b).as_constant(), Some(Constant::Int(-1)));
+        assert_eq!((a & b).as_constant(), Some(Constant::Int(-7 & 2)));
+        assert_eq!((a | b).as_constant(), Some(Constant::Int(-7 | 2)));
+        assert_eq!((a ^ b).as_constant(), Some(Constant::Int(-7 ^ 2)));
+        assert_eq!((a << b).as_constant(), Some(Constant::Int(-28)));
+        assert_eq!((a >> b).as_constant(), Some(Constant::Int(-2)));
+        let zero = func.insn_of(0i32);
+        assert!(!(a / zero).is_constant());
+        assert!(!(a % zero).is_constant());
+        let min = func.insn_of(i32::MIN);
+        assert!(!(min / func.insn_of(-1i32)).is_constant());
+        func.insn_return(a / b);
+    }, assert_eq!(func(), -3));
+}
+#[test]
+fn test_params() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32, f64, u8) -> ()>());
+    let types: Vec<_> = func.params().map(|param| format!("{:?}", param.get_type())).collect();
+    assert_eq!(types, ["i32", "f64", "u8"]);
+    assert_eq!(func.params().size_hint(), (3, Some(3)));
+    assert!(func.params().all(|param| param.is_parameter()));
+    assert_eq!(func.param(2), Some(&func[2]));
+}
+#[test]
+fn test_cached_shapes() {
+    assert_eq!(get::<(f64, f64)>(), get::<(f64, f64)>());
+    assert_eq!(get::<(i32, &'static str, u8)>(), get::<(i32, &'static str, u8)>());
+    assert!(get::<(f64, f64)>() != get::<(f64, f32)>());
+    assert_eq!(get::<fn(f64, f64) -> f64>(), get::<extern fn(f64, f64) -> f64>());
+    assert!(get::<fn(f64, f64) -> f64>() != get::<fn((f64, f64)) -> f64>());
+    assert_eq!(get::<fn(&'static u8)>(), get::<fn(&'static u8)>());
+}
+#[test]
+fn test_cached_types_unchanged() {
+    let mut pair_t = get::<(f64, i32)>().into_owned();
+    pair_t.set_names(&["a", "b"]);
+    assert_eq!(pair_t.get_field("b").unwrap().get_offset(), 8);
+    assert!(get::<(f64, i32)>().get_field("a").is_none());
+    assert!(&*pair_t != &*get::<(f64, i32)>());
+}
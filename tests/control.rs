@@ -0,0 +1,215 @@
+#![feature(plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+use jit::*;
+use std::str::Chars;
+
+fn compile_bf<'a>(func: &UncompiledFunction<'a>, data: &'a Val, code: &mut Chars) {
+    let ubyte = typecs::get_ubyte();
+    while let Some(c) = code.next() {
+        match c {
+            '>' => func.insn_store(data, data + 1usize),
+            '<' => func.insn_store(data, data - 1usize),
+            '+' | '-' => {
+                let value = func.insn_load_relative(data, 0, ubyte);
+                let value = if c == '+' { value + 1u8 } else { value - 1u8 };
+                func.insn_store_relative(data, 0, func.insn_convert(value, ubyte, false));
+            },
+            '[' => func.insn_while(|| func.insn_load_relative(data, 0, ubyte), || {
+                compile_bf(func, data, code)
+            }),
+            ']' => return,
+            _ => ()
+        }
+    }
+}
+
+fn run_bf(code: &str) -> [u8; 8] {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u8)>());
+    let data = func.param(0).unwrap();
+    compile_bf(&func, data, &mut code.chars());
+    func.insn_default_return();
+    let mut cells = [0u8; 8];
+    func.compile().with(|func: extern fn(*mut u8)| func(cells.as_mut_ptr()));
+    cells
+}
+
+#[test]
+fn test_brainfuck_loops() {
+    assert_eq!(run_bf("++++++[>+++++++<-]>")[1], 42);
+    assert_eq!(run_bf("+++[>++[>+++<-]<-]")[2], 18);
+    assert_eq!(run_bf("++>+++++[<+>-]"), [7, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn test_break_continue() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(limit: i32) -> i32 {
+        let i = Val::new(func, &get::<i32>());
+        let sum = Val::new(func, &get::<i32>());
+        func.insn_store(i, func.insn_of(0i32));
+        func.insn_store(sum, func.insn_of(0i32));
+        func.insn_loop_with(|lp| {
+            func.insn_store(i, i + 1i32);
+            lp.break_if(func.insn_gt(i, limit));
+            // skip multiples of three
+            func.insn_if(func.insn_eq(func.insn_rem(i, func.insn_of(3i32)), func.insn_of(0i32)), || lp.continue_());
+            func.insn_store(sum, sum + i);
+        });
+        func.insn_return(sum);
+    }, {
+        assert_eq!(func(10), 1 + 2 + 4 + 5 + 7 + 8 + 10);
+        assert_eq!(func(0), 0);
+    });
+}
+
+#[test]
+fn test_for_range() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(end: i32) -> i32 {
+        let sum = Val::new(func, &get::<i32>());
+        func.insn_store(sum, func.insn_of(0i32));
+        func.insn_for_range(func.insn_of(0i32), end, func.insn_of(2i32), |i, lp| {
+            lp.continue_if(func.insn_eq(i, func.insn_of(4i32)));
+            func.insn_store(sum, sum + i);
+        });
+        func.insn_for_range(func.insn_of(10i32), func.insn_of(0i32), func.insn_of(-3i32), |i, _| {
+            func.insn_store(sum, sum + i * 100i32);
+        });
+        func.insn_return(sum);
+    }, {
+        assert_eq!(func(10), 0 + 2 + 6 + 8 + (10 + 7 + 4 + 1) * 100);
+        assert_eq!(func(0), 2200);
+    });
+}
+
+#[test]
+fn test_for_range_variable_step() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(step: i32) -> i32 {
+        let sum = func.var::<i32>(0);
+        func.insn_for_range(func.insn_of(0i32), step * 5i32, step, |i, _| {
+            sum.set(sum.get() + i);
+        });
+        func.insn_return(sum.get());
+    }, {
+        assert_eq!(func(2), 0 + 2 + 4 + 6 + 8);
+        assert_eq!(func(-3), 0 - 3 - 6 - 9 - 12);
+    });
+}
+
+#[test]
+fn test_select() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: i32) -> f64 {
+        let zero = func.insn_of(0i32);
+        let negative = func.insn_lt(x, zero);
+        func.insn_return(func.insn_select(negative, || func.insn_of(-0.5f64), || func.insn_sqrt(func.insn_convert(x, typecs::get_float64(), false))));
+    }, {
+        assert_eq!(func(-3), -0.5);
+        assert_eq!(func(16), 4.0);
+    });
+}
+
+#[test]
+fn test_cold() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: i32) -> i32 {
+        let y = func.var::<i32>(1);
+        func.insn_if(func.insn_lt(x, func.insn_of(0i32)), || func.insn_cold(|| {
+            y.set(func.insn_neg(x));
+        }));
+        func.insn_return(y.get() * 10i32);
+    }, {
+        assert_eq!(func(4), 10);
+        assert_eq!(func(-3), 30);
+    });
+}
+
+#[test]
+fn test_cold_straight_line() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: i32) -> i32 {
+        let y = func.var::<i32>(0);
+        y.set(x);
+        func.insn_cold(|| y.set(y.get() * 3i32));
+        y.set(y.get() + 1i32);
+        func.insn_cold(|| y.set(y.get() * 2i32));
+        func.insn_return(y.get());
+    }, {
+        assert_eq!(func(2), 14);
+        assert_eq!(func(-1), -4);
+    });
+}
+
+#[test]
+fn test_move_blocks_to_start() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn() -> i32 {
+        let y = Val::new(func, &get::<i32>());
+        // keep the code below out of the entry block, which isn't moved past
+        func.insn_new_block();
+        assert_eq!(func.get_current().unwrap().iter().count(), 0);
+        func.insn_store(y, y * 10i32 + 1i32);
+        let mut start = Label::new(func);
+        let mut end = Label::new(func);
+        func.insn_label(&mut start);
+        func.insn_store(y, func.insn_of(2i32));
+        func.insn_label(&mut end);
+        func.insn_move_blocks_to_start(&start, &end);
+        func.insn_return(y);
+    }, assert_eq!(func(), 21));
+}
+
+#[test]
+fn test_address_of_label() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: i32) -> usize {
+        let mut first = Label::new(func);
+        let mut second = Label::new(func);
+        let first_addr = func.insn_address_of_label(&mut first);
+        assert!(first_addr.get_type().is_pointer());
+        func.insn_if(func.insn_neq(x, func.insn_of(0i32)), || {
+            func.insn_return(func.insn_address_of_label(&mut second));
+        });
+        func.insn_return(first_addr);
+        func.insn_label(&mut first);
+        func.insn_default_return();
+        func.insn_label(&mut second);
+        func.insn_default_return();
+    }, {
+        assert!(func(0) != 0 && func(1) != 0);
+        assert!(func(0) != func(1));
+        assert_eq!(func(0), func(0));
+    });
+}
+
+#[test]
+fn test_macro_break_continue() {
+    let mut ctx = Context::<()>::new();
+    jit!(&mut ctx, |x: i32| -> i32 {
+        loop {
+            loop {
+                if x > 10i32 {
+                    break
+                }
+                if x < 0i32 {
+                    continue
+                }
+                return 1i32
+            }
+            if x > 100i32 {
+                break
+            }
+            return 2i32
+        }
+        3i32
+    }, |func: extern fn(i32) -> i32| {
+        assert_eq!(func(5), 1);
+        assert_eq!(func(20), 2);
+        assert_eq!(func(200), 3);
+    });
+}
@@ -0,0 +1,66 @@
+extern crate jit;
+extern crate libc;
+use jit::*;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::mem;
+use std::process::Command;
+
+extern fn scale(x: f64, factor: i32) -> f64 {
+    x * factor as f64
+}
+
+// build a shared library that calls `scale` without defining it, named after
+// the test and process so concurrent runs don't share files, or give `None`
+// if there's no C compiler to build it with
+fn build_library(test: &str) -> Option<String> {
+    let dir = env::temp_dir();
+    let name = format!("{}_{}", test, unsafe { libc::getpid() });
+    let source = dir.join(format!("{}.c", name));
+    let library = dir.join(format!("{}.so", name));
+    File::create(&source).unwrap()
+        .write_all(b"extern double scale(double x, int factor);\n\
+                     double triple(double x) { return scale(x, 3); }\n").unwrap();
+    let status = Command::new("cc")
+        .arg("-shared").arg("-fPIC").arg("-nostdlib")
+        .arg("-o").arg(&library).arg(&source)
+        .status();
+    fs::remove_file(&source).unwrap();
+    match status {
+        Ok(status) => {
+            assert!(status.success());
+            Some(library.to_str().unwrap().to_owned())
+        },
+        Err(_) => None
+    }
+}
+
+#[test]
+fn test_resolve_symbol() {
+    let path = match build_library("jit_resolve_symbol") {
+        Some(path) => path,
+        None => return
+    };
+    let mut ctx = Context::<()>::new();
+    ctx.register("scale", scale as extern fn(f64, i32) -> f64);
+    let elf = ReadElf::new(&path).ok().expect("the library should be readable");
+    let triple: extern fn(f64) -> f64 = unsafe { mem::transmute(elf.get_symbol::<u8>("triple")) };
+    elf.add_to_context(&ctx);
+    assert!(ReadElf::resolve_all(&ctx, true));
+    assert_eq!(triple(1.5), 4.5);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_resolve_missing_symbol() {
+    let path = match build_library("jit_resolve_missing_symbol") {
+        Some(path) => path,
+        None => return
+    };
+    let ctx = Context::<()>::new();
+    let elf = ReadElf::new(&path).ok().expect("the library should be readable");
+    elf.add_to_context(&ctx);
+    assert!(!ReadElf::resolve_all(&ctx, false));
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,132 @@
+#![feature(custom_attribute, custom_derive, plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+use jit::*;
+
+#[repr(u8)]
+#[derive(Clone, Compile, Copy, Debug, PartialEq, Eq)]
+enum ErrCode {
+    Negative,
+    NotANumber
+}
+
+#[test]
+fn test_option_type() {
+    let opt_t = get::<Option<i64>>();
+    assert_eq!(opt_t.get_size(), std::mem::size_of::<Option<i64>>());
+    assert_eq!(opt_t.get_variant_field(1, 0).unwrap().1, typecs::get_long());
+    assert!(opt_t.get_variant_field(0, 0).is_none());
+}
+
+#[test]
+fn test_option_round_trip() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: i64) -> Option<i64> {
+        func.insn_if(func.insn_gt(x, func.insn_of(0i64)), || {
+            func.insn_return(func.insn_make_some(x));
+        });
+        func.insn_return(func.insn_make_none(&get::<i64>()));
+    }, {
+        assert_eq!(func(42), Some(42));
+        assert_eq!(func(0), None);
+        assert_eq!(func(-3), None);
+    });
+    jit_func!(&mut ctx, func, fn(x: Option<i64>) -> i64 {
+        func.insn_return(func.insn_unwrap_or(x, func.insn_of(-1i64)));
+    }, {
+        assert_eq!(func(Some(42)), 42);
+        assert_eq!(func(None), -1);
+    });
+}
+
+#[test]
+fn test_option_constant() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn() -> Option<f64> {
+        func.insn_return(func.insn_of(Some(2.5f64)));
+    }, assert_eq!(func(), Some(2.5)));
+}
+
+#[test]
+fn test_result_round_trip() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: f64) -> Result<f64, ErrCode> {
+        func.insn_if(func.insn_is_nan(x), || {
+            func.insn_return(func.insn_make_err(&get::<f64>(), func.insn_of(ErrCode::NotANumber)));
+        });
+        func.insn_if(func.insn_lt(x, func.insn_of(0f64)), || {
+            func.insn_return(func.insn_make_err(&get::<f64>(), func.insn_of(ErrCode::Negative)));
+        });
+        func.insn_return(func.insn_make_ok(func.insn_sqrt(x), &get::<ErrCode>()));
+    }, {
+        assert_eq!(func(16.0), Ok(4.0));
+        assert_eq!(func(-1.0), Err(ErrCode::Negative));
+        assert_eq!(func(std::f64::NAN), Err(ErrCode::NotANumber));
+    });
+}
+
+#[repr(C, u8)]
+#[derive(Clone, Compile, Copy, Debug, PartialEq)]
+enum Shape {
+    Circle(f64),
+    Rect {
+        w: f64,
+        h: f64
+    },
+    Empty
+}
+
+#[repr(u8)]
+#[derive(Clone, Compile, Copy, Debug, PartialEq)]
+enum Token {
+    Num(i64),
+    Op(u8)
+}
+
+#[test]
+fn test_data_enum_layout() {
+    let shape_t = get::<Shape>();
+    assert_eq!(shape_t.get_size(), std::mem::size_of::<Shape>());
+    assert_eq!(shape_t.get_discriminant().unwrap(), (0, typecs::get_ubyte()));
+    assert_eq!(shape_t.get_variant_field(0, 0).unwrap().0, 8);
+    assert_eq!(shape_t.get_variant_field(1, 1).unwrap().0, 16);
+    assert!(shape_t.get_variant_field(2, 0).is_none());
+    let token_t = get::<Token>();
+    assert_eq!(token_t.get_size(), std::mem::size_of::<Token>());
+    assert_eq!(token_t.get_variant_field(0, 0).unwrap().0, 8);
+    assert_eq!(token_t.get_variant_field(1, 0).unwrap().0, 1);
+}
+
+#[test]
+fn test_data_enum_round_trip() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn() -> Shape {
+        func.insn_return(func.insn_of(Shape::Rect { w: 2.0, h: 3.0 }));
+    }, assert_eq!(func(), Shape::Rect { w: 2.0, h: 3.0 }));
+    jit_func!(&mut ctx, func, fn(shape: Shape) -> u8 {
+        func.insn_return(func.insn_discriminant(shape));
+    }, {
+        assert_eq!(func(Shape::Circle(1.0)), 0);
+        assert_eq!(func(Shape::Empty), 2);
+    });
+    jit_func!(&mut ctx, func, fn() -> Token {
+        func.insn_return(func.insn_of(Token::Op(b'+')));
+    }, assert_eq!(func(), Token::Op(b'+')));
+}
+
+#[test]
+fn test_discriminant_needs_enum() {
+    let u8_t = get::<u8>();
+    let f64_t = get::<f64>();
+    let variant_t = Type::new_struct(&mut [&u8_t, &f64_t]);
+    let union_t = Type::new_union(&mut [&variant_t, &f64_t]);
+    assert!(union_t.get_discriminant().is_none());
+    assert!(union_t.get_variant_field(0, 0).is_none());
+    let tagged_t = Type::new_struct(&mut [&u8_t, &union_t]);
+    assert!(tagged_t.get_discriminant().is_none());
+    let enum_t = Type::new_c_enum(&u8_t, &mut [&variant_t]);
+    assert_eq!(enum_t.get_discriminant().unwrap(), (0, &*u8_t));
+    assert_eq!(format!("{:?}", get::<Option<u8>>()), "union((u8), (u8, u8))");
+}
@@ -0,0 +1,52 @@
+#![feature(plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+use jit::*;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+extern fn answer() -> i32 {
+    42
+}
+extern fn weighted(a: i32, b: i64, c: f64, d: u8, e: f32, f: i32) -> f64 {
+    a as f64 + b as f64 * 2.0 + c * 3.0 + d as f64 * 4.0 + e as f64 * 5.0 + f as f64 * 6.0
+}
+
+static CALLS: AtomicUsize = ATOMIC_USIZE_INIT;
+extern fn count(n: usize) {
+    CALLS.fetch_add(n, Ordering::SeqCst);
+}
+
+#[test]
+fn test_call_native_no_args() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn() -> i32 {
+        let f: extern fn() -> i32 = answer;
+        func.insn_return(func.insn_call_native(Some("answer"), f, [], flags::CallFlags::NO_THROW));
+    }, assert_eq!(func(), 42));
+}
+
+#[test]
+fn test_call_native_many_args() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(a: i32, f: i32) -> f64 {
+        let native: extern fn(i32, i64, f64, u8, f32, i32) -> f64 = weighted;
+        let args = [a, func.insn_of(2i64), func.insn_of(0.5f64), func.insn_of(3u8), func.insn_of(1.5f32), f];
+        func.insn_return(func.insn_call_native(Some("weighted"), native, args, flags::CallFlags::NO_THROW));
+    }, assert_eq!(func(1, 10), 1.0 + 4.0 + 1.5 + 12.0 + 7.5 + 60.0));
+}
+
+#[test]
+fn test_call_native_void() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(n: usize) -> () {
+        let native: extern fn(usize) = count;
+        func.insn_call_native(Some("count"), native, [n], flags::CallFlags::NO_THROW);
+        func.insn_default_return();
+    }, {
+        func(3);
+        func(4);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 7);
+    });
+}
@@ -0,0 +1,59 @@
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_nested_two_levels() {
+    let mut ctx = Context::<()>::new();
+    let outer = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    let x = outer.param(0).unwrap();
+    let y = outer.insn_mul(x, outer.insn_of(2.0f64));
+    let flags = flags::CallFlags::NO_THROW;
+    {
+        let inner = UncompiledFunction::new_nested_in(&get::<fn(f64) -> f64>(), &outer);
+        let z = inner.param(0).unwrap();
+        {
+            let innermost = UncompiledFunction::new_nested_in(&get::<fn() -> f64>(), &inner);
+            assert!(innermost.get_nested_parent() == Some(&*inner));
+            // reads from both its parent and its grandparent
+            innermost.insn_return(innermost.import(x) + innermost.import(y) + innermost.import(z));
+            inner.insn_return(inner.insn_call_nested(Some("innermost"), &innermost, &mut [], flags) + 1.0f64);
+            innermost.compile();
+        }
+        outer.insn_return(outer.insn_call_nested(Some("inner"), &inner, &mut [outer.insn_of(0.25f64)], flags));
+        inner.compile();
+    }
+    outer.compile().with(|func: extern fn(f64) -> f64| {
+        assert_eq!(func(1.5), 1.5 + 3.0 + 0.25 + 1.0);
+    });
+}
+
+#[test]
+fn test_call_sibling() {
+    let mut ctx = Context::<()>::new();
+    let outer = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let x = outer.param(0).unwrap();
+    let flags = flags::CallFlags::NO_THROW;
+    {
+        let first = UncompiledFunction::new_nested_in(&get::<fn() -> i32>(), &outer);
+        first.insn_return(first.import(x) + 1i32);
+        let second = UncompiledFunction::new_nested_in(&get::<fn() -> i32>(), &outer);
+        second.insn_return(second.insn_call_nested(Some("first"), &first, &mut [], flags) * 10i32);
+        outer.insn_return(outer.insn_call_nested(Some("second"), &second, &mut [], flags));
+        first.compile();
+        second.compile();
+    }
+    outer.compile().with(|func: extern fn(i32) -> i32| {
+        assert_eq!(func(4), 50);
+    });
+}
+
+#[test]
+#[should_panic]
+fn test_import_unrelated() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let mut other_ctx = Context::<()>::new();
+    let other = UncompiledFunction::new(&mut other_ctx, &get::<fn(i32) -> i32>());
+    let nested = UncompiledFunction::new_nested_in(&get::<fn() -> i32>(), &func);
+    nested.import(other.param(0).unwrap());
+}
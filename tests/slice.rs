@@ -0,0 +1,96 @@
+#![feature(plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+extern crate libc;
+use jit::*;
+use libc::c_void;
+use std::ptr;
+use std::thread;
+
+static DATA:[i32; 4] = [4, 8, 15, 16];
+
+#[test]
+fn test_slice_type() {
+    let slice_t = get::<&'static [i32]>();
+    assert!(slice_t.is_struct());
+    let mut fields = slice_t.fields();
+    let data = fields.next().unwrap();
+    assert_eq!(data.get_type().get_ref().unwrap(), &get::<i32>() as &Ty);
+    assert_eq!(fields.next().unwrap().get_type(), &get::<usize>() as &Ty);
+    assert_eq!(slice_t.get_size(), std::mem::size_of::<&'static [i32]>());
+}
+
+#[test]
+fn test_index_checked() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(index: usize) -> i32 {
+        let slice = func.insn_of(&DATA[..]);
+        func.insn_return(func.insn_index_checked(slice, index));
+    }, {
+        assert_eq!(func(0), 4);
+        assert_eq!(func(3), 16);
+    });
+}
+
+#[test]
+fn test_index_out_of_bounds() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> i32>());
+    let slice = func.insn_of(&DATA[..]);
+    func.insn_return(func.insn_index_checked(slice, func.param(0).unwrap()));
+    let func = func.compile();
+    let index = |mut index: usize| unsafe {
+        let mut result = 0i32;
+        func.apply(&mut [&mut index as *mut usize as *mut c_void], &mut result as *mut i32 as *mut c_void)
+            .map(|()| result)
+    };
+    assert_eq!(index(2), Ok(15));
+    assert_eq!(index(4), Err(BuiltinException::OutOfBounds));
+    assert_eq!(index(!0), Err(BuiltinException::OutOfBounds));
+}
+
+#[test]
+fn test_check_null() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static i32) -> i32>());
+    let ptr = func.param(0).unwrap();
+    func.insn_check_null(ptr);
+    func.insn_return(func.insn_load_relative(ptr, 0, &get::<i32>()));
+    let func = func.compile();
+    let load = |mut ptr: *const i32| unsafe {
+        let mut result = 0i32;
+        func.apply(&mut [&mut ptr as *mut *const i32 as *mut c_void], &mut result as *mut i32 as *mut c_void)
+            .map(|()| result)
+    };
+    assert_eq!(load(&DATA[1]), Ok(8));
+    assert_eq!(load(ptr::null()), Err(BuiltinException::NullReference));
+}
+
+#[test]
+fn test_index_out_of_bounds_threads() {
+    let threads = (0..4).map(|_| thread::spawn(|| {
+        let mut ctx = Context::<()>::new();
+        let func = UncompiledFunction::new(&mut ctx, &get::<fn(usize) -> i32>());
+        let slice = func.insn_of(&DATA[..]);
+        func.insn_return(func.insn_index_checked(slice, func.param(0).unwrap()));
+        let func = func.compile();
+        for i in 0..100 {
+            let mut index = i % 8;
+            let mut result = 0i32;
+            let applied = unsafe {
+                func.apply(&mut [&mut index as *mut usize as *mut c_void], &mut result as *mut i32 as *mut c_void)
+            };
+            if index < DATA.len() {
+                assert_eq!(applied, Ok(()));
+                assert_eq!(result, DATA[index]);
+            } else {
+                assert_eq!(applied, Err(BuiltinException::OutOfBounds));
+            }
+        }
+    })).collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
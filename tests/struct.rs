@@ -30,3 +30,164 @@ fn test_struct() {
         })
     }
 }
+
+#[repr(C)]
+#[derive(Compile)]
+pub struct Point<T> {
+    x: T,
+    y: T
+}
+
+#[repr(C)]
+#[derive(Compile)]
+pub struct Pair(u8, f64);
+
+#[repr(C)]
+#[derive(Compile)]
+pub struct Unit;
+
+#[test]
+fn test_generic_struct() {
+    let point_t = get::<Point<f32>>();
+    let x = point_t.get_field("x").unwrap();
+    let y = point_t.get_field("y").unwrap();
+    assert_eq!(x.get_type(), typecs::get_float32());
+    assert_eq!(x.get_offset(), 0);
+    assert_eq!(y.get_offset(), 4);
+    assert_eq!(point_t.get_size(), std::mem::size_of::<Point<f32>>());
+}
+
+#[test]
+fn test_tuple_struct() {
+    let pair_t = get::<Pair>();
+    let mut fields = pair_t.fields();
+    assert_eq!(fields.next().unwrap().get_name(), None);
+    assert_eq!(fields.next().unwrap().get_offset(), 8);
+    assert_eq!(pair_t.get_size(), std::mem::size_of::<Pair>());
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn() -> f64 {
+        let pair = func.insn_of(Pair(3, 1.5));
+        let addr = func.insn_address_of(pair);
+        func.insn_return(func.insn_load_relative(addr, 8, typecs::get_float64()));
+    }, assert_eq!(func(), 1.5));
+}
+
+#[test]
+fn test_unit_struct() {
+    assert_eq!(get::<Unit>().fields().count(), 0);
+}
+
+#[test]
+fn test_field_access() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: f64, y: f64) -> f64 {
+        let point = StructBuilder::new(func, &get::<Point<f64>>())
+            .field("x", x)
+            .field("y", y)
+            .build();
+        func.insn_store_field(point, "y", func.insn_of(4.0f64));
+        func.insn_return(point.field(func, "x") + func.insn_load_field(point, "y"));
+    }, assert_eq!(func(1.5, 2.0), 5.5));
+}
+
+#[repr(C)]
+#[derive(Compile)]
+pub struct Segment {
+    start: Point<f64>,
+    end: Point<f64>
+}
+
+#[test]
+#[should_panic(expected = "Bad value for field start")]
+fn test_store_field_mismatched_struct() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static Segment)>());
+    let start = StructBuilder::new(&func, &get::<Point<f32>>())
+        .field("x", func.insn_of(1.0f32))
+        .field("y", func.insn_of(2.0f32))
+        .build();
+    func.insn_store_field(func.param(0).unwrap(), "start", start);
+}
+
+static CORNER: Point<i32> = Point { x: 3, y: 4 };
+
+#[test]
+fn test_load_small() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(point: &'static Point<i32>) -> i32 {
+        let point_t = get::<Point<i32>>();
+        let value = func.insn_load_relative(point, 0, &point_t);
+        let small = func.insn_load_small(value);
+        assert!(small.get_type().structurally_eq(&point_t));
+        func.insn_return(func.insn_load_field(small, "y"));
+    }, assert_eq!(func(&CORNER), 4));
+}
+
+#[test]
+#[should_panic]
+fn test_load_small_primitive() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32)>());
+    func.insn_load_small(func.param(0).unwrap());
+}
+
+#[test]
+#[should_panic]
+fn test_struct_builder_missing_field() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn()>());
+    StructBuilder::new(&func, &get::<Point<f64>>())
+        .field("x", func.insn_of(1.0f64))
+        .build();
+}
+
+#[repr(packed)]
+pub struct Packet {
+    kind: u8,
+    len: u32,
+    checksum: u16
+}
+
+#[test]
+fn test_struct_layout() {
+    let u8_t = get::<u8>();
+    let u16_t = get::<u16>();
+    let u32_t = get::<u32>();
+    let packet_t = StructLayout::new()
+        .field("kind", &u8_t)
+        .field("len", &u32_t)
+        .field("checksum", &u16_t)
+        .packed()
+        .build_for::<Packet>()
+        .unwrap();
+    assert_eq!(packet_t.get_field("len").unwrap().get_offset(), 1);
+    assert_eq!(packet_t.get_field("checksum").unwrap().get_offset(), 5);
+    assert_eq!(packet_t.get_alignment(), 1);
+    let padded = StructLayout::new()
+        .field("kind", &u8_t)
+        .field_at("len", &u32_t, 8)
+        .alignment(16);
+    assert_eq!(padded.build().get_size(), 16);
+    assert_eq!(padded.build_for::<Packet>().err(), Some(LayoutError::Size(16, 7)));
+}
+
+extern fn point_sum(point: Point<f64>) -> f64 {
+    point.x + point.y
+}
+
+#[test]
+fn test_structural_eq() {
+    let point_t = get::<Point<f64>>();
+    let other_t = get::<Point<f64>>();
+    assert!(point_t != other_t);
+    assert!(point_t.structurally_eq(&other_t));
+    assert!(!point_t.structurally_eq(&get::<Point<f32>>()));
+    let ctx = Context::<()>::new();
+    assert_eq!(ctx.get_type::<Point<f64>>(), ctx.intern(&point_t));
+    let mut ctx = ctx;
+    jit_func!(&mut ctx, func, fn() -> f64 {
+        let sig = Type::new_signature(Abi::CDecl, &get::<f64>(), &mut [&point_t]);
+        let point = func.insn_of(Point { x: 1.5f64, y: 2.0 });
+        func.insn_return(func.insn_call_native1(Some("point_sum"), point_sum, &sig, [point], flags::CallFlags::NO_THROW));
+    }, assert_eq!(func(), 3.5));
+}
@@ -0,0 +1,121 @@
+#![feature(plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_switch_dense() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(op: i32, x: i32) -> i32 {
+        func.insn_switch(op, &[
+            (-1, &|| func.insn_return(func.insn_neg(x))),
+            (0, &|| func.insn_return(x + 1i32)),
+            (1, &|| func.insn_return(x - 1i32)),
+            (2, &|| func.insn_return(x * 2i32)),
+            (4, &|| func.insn_return(x * x))
+        ], || func.insn_return(func.insn_of(0i32)));
+    }, {
+        assert_eq!(func(-1, 5), -5);
+        assert_eq!(func(0, 5), 6);
+        assert_eq!(func(1, 5), 4);
+        assert_eq!(func(2, 5), 10);
+        assert_eq!(func(3, 5), 0);
+        assert_eq!(func(4, 5), 25);
+        assert_eq!(func(5, 5), 0);
+        assert_eq!(func(-100, 5), 0);
+    });
+}
+
+#[test]
+fn test_switch_sparse() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: i64) -> i64 {
+        let result = Val::new(func, &get::<i64>());
+        func.insn_switch(x, &[
+            (-1000000, &|| func.insn_store(result, func.insn_of(1i64))),
+            (7, &|| func.insn_store(result, func.insn_of(2i64))),
+            (300, &|| func.insn_store(result, func.insn_of(3i64))),
+            (4096, &|| func.insn_store(result, func.insn_of(4i64))),
+            (1 << 40, &|| func.insn_store(result, func.insn_of(5i64)))
+        ], || func.insn_store(result, func.insn_of(-1i64)));
+        func.insn_return(result);
+    }, {
+        assert_eq!(func(-1000000), 1);
+        assert_eq!(func(7), 2);
+        assert_eq!(func(300), 3);
+        assert_eq!(func(4096), 4);
+        assert_eq!(func(1 << 40), 5);
+        assert_eq!(func(8), -1);
+        assert_eq!(func(0), -1);
+    });
+}
+
+#[test]
+fn test_switch_unsigned() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: u64) -> i64 {
+        let result = Val::new(func, &get::<i64>());
+        func.insn_switch(x, &[
+            (-1, &|| func.insn_store(result, func.insn_of(1i64))),
+            (5, &|| func.insn_store(result, func.insn_of(2i64))),
+            (1 << 40, &|| func.insn_store(result, func.insn_of(3i64))),
+            (-2, &|| func.insn_store(result, func.insn_of(4i64))),
+            (100, &|| func.insn_store(result, func.insn_of(5i64)))
+        ], || func.insn_store(result, func.insn_of(-1i64)));
+        func.insn_return(result);
+    }, {
+        assert_eq!(func(!0), 1);
+        assert_eq!(func(5), 2);
+        assert_eq!(func(1 << 40), 3);
+        assert_eq!(func(!0 - 1), 4);
+        assert_eq!(func(100), 5);
+        assert_eq!(func(0), -1);
+        assert_eq!(func(1 << 63), -1);
+    });
+}
+
+#[test]
+#[should_panic(expected = "doesn't fit")]
+fn test_switch_case_too_big() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i8)>());
+    let x = func.param(0).unwrap();
+    func.insn_switch(x, &[(1, &|| ()), (300, &|| ())], || ());
+}
+
+#[test]
+#[should_panic(expected = "doesn't fit")]
+fn test_switch_negative_unsigned_case() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(u8)>());
+    let x = func.param(0).unwrap();
+    func.insn_switch(x, &[(-1, &|| ())], || ());
+}
+
+#[test]
+#[should_panic(expected = "more than once")]
+fn test_switch_duplicate() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32)>());
+    let x = func.param(0).unwrap();
+    func.insn_switch(x, &[(1, &|| ()), (1, &|| ())], || ());
+}
+
+#[test]
+fn test_jump_table_macro() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: usize) -> usize {
+        jit!(func, jump_table(x, zero, one));
+        func.insn_return(func.insn_of(100usize));
+        func.insn_label(&mut zero);
+        func.insn_return(func.insn_of(10usize));
+        func.insn_label(&mut one);
+        func.insn_return(func.insn_of(11usize));
+    }, {
+        assert_eq!(func(0), 10);
+        assert_eq!(func(1), 11);
+        assert_eq!(func(2), 100);
+    });
+}
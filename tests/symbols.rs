@@ -0,0 +1,42 @@
+#![feature(plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+use jit::*;
+
+extern fn scale(x: f64, factor: i32) -> f64 {
+    x * factor as f64
+}
+extern fn offset(x: f64) -> f64 {
+    x + 0.5
+}
+
+#[test]
+fn test_call_symbol() {
+    let mut ctx = Context::<()>::new();
+    ctx.register("scale", scale as extern fn(f64, i32) -> f64);
+    ctx.register("offset", offset as extern fn(f64) -> f64);
+    assert_eq!(ctx.symbols().len(), 2);
+    assert!(ctx.symbols().get("scale").unwrap().get_signature()
+               .structurally_eq(&get::<fn(f64, i32) -> f64>()));
+    jit_func!(&mut ctx, func, fn(x: f64) -> f64 {
+        let flags = flags::CallFlags::NO_THROW;
+        let scaled = func.insn_call_symbol("scale", &mut [x, func.insn_of(4i32)], flags).unwrap();
+        func.insn_return(func.insn_call_symbol("offset", &mut [scaled], flags).unwrap());
+    }, assert_eq!(func(2.0), 8.5));
+    assert!(ReadElf::resolve_all(&ctx, false));
+}
+
+#[test]
+fn test_missing_symbol() {
+    let mut ctx = Context::<()>::new();
+    assert_eq!(ctx.symbols().get("offset").err(), Some(SymbolError::NotFound("offset".to_owned())));
+    ctx.register("offset", offset as extern fn(f64) -> f64);
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    let flags = flags::CallFlags::NO_THROW;
+    assert_eq!(func.insn_call_symbol("print", &mut [func.param(0).unwrap()], flags).err(),
+               Some(SymbolError::NotFound("print".to_owned())));
+    assert_eq!(func.insn_call_symbol("offset", &mut [], flags).err(),
+               Some(SymbolError::ArgCount(1, 0)));
+}
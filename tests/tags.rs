@@ -21,6 +21,12 @@ fn test_context_bad_tags() {
     ctx[1] = 33;
     assert_eq!(ctx[2], 3);
 }
+#[test]
+#[should_panic(expected = "reserved for the symbol table")]
+fn test_context_symbol_tag() {
+    let mut ctx = Context::<isize>::new();
+    ctx[0x73796d73] = 3;
+}
 
 #[derive(Debug, Eq, PartialEq)]
 struct PanicDrop(isize);
@@ -51,3 +57,44 @@ fn test_tags() {
     assert!(new_pos_t.get_tagged_data() == Some(&42));
     assert!(new_pos_t.get_tagged_type() == &*pos_t);
 }
+#[test]
+fn test_debug_tags() {
+    assert_eq!(format!("{:?}", get::<bool>()), "bool");
+    assert_eq!(format!("{:?}", get::<&'static char>()), "&mut char");
+    assert_eq!(format!("{:?}", get::<Pos>()), "{x: f64, y: f64}");
+    let pos_t = get::<Pos>();
+    let tagged = TaggedType::new(&pos_t, kind::TypeKind::Const, Box::new(()));
+    assert_eq!(format!("{:?}", &*tagged), "const {x: f64, y: f64}");
+    let tagged = TaggedType::new(&pos_t, kind::TypeKind::Pointer, Box::new(42));
+    assert_eq!(format!("{:?}", &*tagged), "tagged<17>({x: f64, y: f64})");
+    let sig_t = get::<fn(bool, f64) -> Pos>();
+    assert_eq!(format!("{}", sig_t), "extern \"C\" fn(bool, f64) -> {x: f64, y: f64}");
+}
+#[test]
+fn test_structural_eq_tags() {
+    use std::hash::{Hash, Hasher, SipHasher};
+    fn hash(ty: &Ty) -> u64 {
+        let mut hasher = SipHasher::new();
+        ty.hash(&mut hasher);
+        hasher.finish()
+    }
+    let pos_t = get::<Pos>();
+    let a = TaggedType::new(&pos_t, kind::TypeKind::Const, Box::new(1));
+    let b = TaggedType::new(&pos_t, kind::TypeKind::Const, Box::new(2));
+    assert!(a.structurally_eq(&a));
+    assert!(!a.structurally_eq(&b));
+    assert!(hash(&a) != hash(&b));
+    let ptr_a = Type::new_pointer(&a);
+    assert!(!ptr_a.structurally_eq(&Type::new_pointer(&b)));
+    assert!(ptr_a.structurally_eq(&Type::new_pointer(&a)));
+    assert_eq!(hash(&ptr_a), hash(&Type::new_pointer(&a)));
+}
+#[test]
+fn test_cached_shape_tags() {
+    let pos_t = get::<Pos>();
+    let a = TaggedType::new(&pos_t, kind::TypeKind::Const, Box::new(1));
+    let b = TaggedType::new(&pos_t, kind::TypeKind::Const, Box::new(2));
+    let pair = |x: &Ty| cache_shape(TypeKind::Struct, &[x, x], || Type::new_struct(&mut [x, x]));
+    assert_eq!(pair(&*a), pair(&*a));
+    assert!(pair(&*a) != pair(&*b));
+}
@@ -0,0 +1,40 @@
+#![feature(plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_tail_call_deep() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(n: usize, acc: usize) -> usize {
+        func.insn_if(func.insn_eq(n, func.insn_of(0usize)), || func.insn_return(acc));
+        func.insn_tail_call(func, &mut [n - 1usize, acc + n]).unwrap();
+    }, assert_eq!(func(1000000, 0), 500000500000));
+}
+
+#[test]
+fn test_tail_call_errors() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    let x = func.param(0).unwrap();
+    assert_eq!(func.insn_tail_call(&func, &mut [x, x]), Err(TailCallError::ArgCount(1, 2)));
+    let mut other_ctx = Context::<()>::new();
+    let other = UncompiledFunction::new(&mut other_ctx, &get::<fn(f64) -> i32>());
+    assert_eq!(func.insn_tail_call(&other, &mut [x]), Err(TailCallError::Signature));
+    let nested = UncompiledFunction::new_nested_in(&get::<fn(f64) -> f64>(), &func);
+    assert_eq!(func.insn_tail_call(&nested, &mut [x]), Err(TailCallError::Nested));
+}
+
+#[test]
+fn test_tail_call_addressable() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(f64) -> f64>());
+    let x = func.param(0).unwrap();
+    let y = x * 2.0f64;
+    func.insn_address_of(y);
+    assert!(y.is_addressable());
+    assert_eq!(func.insn_tail_call(&func, &mut [y]), Err(TailCallError::Addressable(0)));
+    assert!(func.insn_tail_call(&func, &mut [x]).is_ok());
+}
@@ -0,0 +1,80 @@
+#![feature(plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_var_loop() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(n: i64) -> i64 {
+        let fact = func.var::<i64>(1);
+        let i = Var::<i64>::new(func, n);
+        func.insn_while(|| func.insn_gt(i.get(), func.insn_of(1i64)), || {
+            fact.set(fact.get() * i.get());
+            i.set(i.get() - 1i64);
+        });
+        func.insn_return(fact.get());
+    }, {
+        assert_eq!(func(1), 1);
+        assert_eq!(func(5), 120);
+        assert_eq!(func(20), 2432902008176640000);
+    });
+}
+
+#[test]
+fn test_var_branches() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: f64) -> f64 {
+        let y = func.var::<f64>(0.0);
+        let old = y.get();
+        func.insn_if_else(func.insn_lt(x, func.insn_of(0.0f64)), || y.set(x * -2.0f64), || y.set(x));
+        func.insn_for_range(func.insn_of(0i32), func.insn_of(3i32), func.insn_of(1i32), |_, _| {
+            y.set(y.get() + 1.0f64);
+        });
+        func.insn_return(y.get() + old);
+    }, {
+        assert_eq!(func(-1.5), 6.0);
+        assert_eq!(func(2.0), 5.0);
+    });
+}
+
+#[test]
+fn test_var_promoted() {
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(x: u8) -> u8 {
+        let y = Var::<u8>::new(func, x);
+        y.set(y.get() + 1u8);
+        func.insn_return(y.get());
+    }, {
+        assert_eq!(func(41), 42);
+        assert_eq!(func(255), 0);
+    });
+    jit_func!(&mut ctx, func, fn(x: i32) -> bool {
+        let negative = func.var::<bool>(false);
+        negative.set(func.insn_lt(x, func.insn_of(0i32)));
+        func.insn_return(negative.get());
+    }, {
+        assert_eq!(func(-3), true);
+        assert_eq!(func(3), false);
+    });
+}
+
+#[test]
+#[should_panic(expected = "Value given to Var::set")]
+fn test_var_wrong_sign() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn()>());
+    let x = func.var::<u8>(0);
+    x.set(func.insn_of(-1i32));
+}
+
+#[test]
+#[should_panic]
+fn test_var_wrong_type() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn()>());
+    let x = func.var::<i64>(0);
+    x.set(1.5f64);
+}
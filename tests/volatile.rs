@@ -0,0 +1,54 @@
+#![feature(plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+use jit::*;
+
+#[test]
+fn test_volatile_values() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(i32) -> i32>());
+    let x = &func[0];
+    assert!(x.is_parameter());
+    assert!(!x.is_volatile());
+    let temp = x + func.insn_of(1i32);
+    assert!(temp.is_temp());
+    assert!(temp.get_block().is_some());
+    temp.set_volatile();
+    assert!(temp.is_volatile());
+    temp.add_ref(&func);
+    func.insn_return(temp);
+}
+
+// changes the port between the two loads, so they can't be merged
+extern fn bump(port: *mut u32) {
+    unsafe {
+        *port += 10;
+    }
+}
+
+#[test]
+fn test_volatile_io() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static u32) -> u32>());
+    func.set_optimization_level(UncompiledFunction::get_max_optimization_level());
+    {
+        let port = func.param(0).unwrap();
+        let u32_t = get::<u32>();
+        let value = func.insn_of(3u32) + func.insn_of(4u32);
+        assert!(value.is_temp());
+        func.insn_store_volatile(port, value);
+        let first = func.insn_load_volatile(port, &u32_t);
+        func.insn_call_native1(Some("bump"), bump, &get::<fn(&'static u32)>(), [port], flags::CallFlags::NO_THROW);
+        let second = func.insn_load_volatile(port, &u32_t);
+        assert!(!port.is_volatile() && !value.is_volatile());
+        assert!(!first.is_volatile() && !second.is_volatile());
+        func.insn_return(first * func.insn_of(100u32) + second);
+    }
+    let mut port = 0u32;
+    func.compile().with(|func: extern fn(*mut u32) -> u32| {
+        assert_eq!(func(&mut port), 717);
+    });
+    assert_eq!(port, 17);
+}
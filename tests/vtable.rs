@@ -0,0 +1,85 @@
+#![feature(custom_derive, custom_attribute, plugin)]
+#![plugin(jit_macros)]
+#[no_link] #[macro_use]
+extern crate jit_macros;
+extern crate jit;
+use jit::*;
+
+#[repr(C)]
+#[derive(Compile)]
+pub struct Shape {
+    vtable: usize,
+    a: f64,
+    b: f64
+}
+
+fn method<'a, F>(ctx: &'a mut Context<()>, body: F)
+    where F:FnOnce(&UncompiledFunction<'a>, &'a Val, &'a Val) -> &'a Val {
+    let func = UncompiledFunction::new(ctx, &get::<fn(&'static Shape) -> f64>());
+    let a = func.insn_load_relative(func.param(0).unwrap(), 8, typecs::get_float64());
+    let b = func.insn_load_relative(func.param(0).unwrap(), 16, typecs::get_float64());
+    let result = body(&func, a, b);
+    func.insn_return(result);
+    func.compile();
+}
+
+#[test]
+fn test_vtable_dispatch() {
+    let mut classes = Context::<()>::new();
+    // square
+    method(&mut classes, |_, a, _| a * a);
+    method(&mut classes, |func, a, _| a * func.insn_of(4.0f64));
+    // rectangle
+    method(&mut classes, |_, a, b| a * b);
+    method(&mut classes, |func, a, b| (a + b) * func.insn_of(2.0f64));
+    let methods = classes.functions().collect::<Vec<_>>();
+    let square = VTableBuilder::new()
+        .method("area", methods[0])
+        .method("perimeter", methods[1])
+        .build();
+    let rect = VTableBuilder::new()
+        .method("area", methods[2])
+        .method("perimeter", methods[3])
+        .build();
+    assert_eq!(square.len(), 2);
+    assert!(square.get_type().structurally_eq(rect.get_type()));
+    assert!(rect.get_function(&classes, 1) == Some(methods[3]));
+    let shapes = [
+        Shape { vtable: square.as_ptr() as usize, a: 3.0, b: 0.0 },
+        Shape { vtable: rect.as_ptr() as usize, a: 2.0, b: 5.0 }
+    ];
+    let mut ctx = Context::<()>::new();
+    jit_func!(&mut ctx, func, fn(shape: &'static Shape) -> f64 {
+        let area = square.get_type().get_field("area").unwrap();
+        let perimeter = square.get_type().get_field("perimeter").unwrap();
+        let flags = flags::CallFlags::NO_THROW;
+        let sum = func.insn_call_vtable(shape, area, &mut [], flags)
+                + func.insn_call_vtable(shape, perimeter, &mut [], flags);
+        func.insn_return(sum);
+    }, {
+        assert_eq!(func(unsafe { &*(&shapes[0] as *const Shape) }), 9.0 + 12.0);
+        assert_eq!(func(unsafe { &*(&shapes[1] as *const Shape) }), 10.0 + 14.0);
+    });
+}
+
+#[test]
+#[should_panic]
+fn test_vtable_arg_count() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static Shape) -> f64>());
+    let area_t = Type::new_pointer(&get::<fn(&'static Shape) -> f64>());
+    let vtable_t = StructLayout::new().field("area", &area_t).build();
+    let area = vtable_t.get_field("area").unwrap();
+    func.insn_call_vtable(func.param(0).unwrap(), area, &mut [func.insn_of(1.0f64)], flags::CallFlags::NO_THROW);
+}
+
+#[test]
+#[should_panic]
+fn test_vtable_arg_type() {
+    let mut ctx = Context::<()>::new();
+    let func = UncompiledFunction::new(&mut ctx, &get::<fn(&'static f64) -> f64>());
+    let area_t = Type::new_pointer(&get::<fn(&'static Shape) -> f64>());
+    let vtable_t = StructLayout::new().field("area", &area_t).build();
+    let area = vtable_t.get_field("area").unwrap();
+    func.insn_call_vtable(func.param(0).unwrap(), area, &mut [], flags::CallFlags::NO_THROW);
+}